@@ -1,22 +1,159 @@
+use proc_macro2::TokenStream;
 use quote::ToTokens;
 use std::fmt;
 use syn::{
-    Expr, Ident, Result, Token,
-    parse::{Parse, ParseStream},
+    Expr, Ident, Path, Result, Token, Type, WherePredicate,
+    parse::{Parse, ParseStream, Parser},
     punctuated::Punctuated,
     token::Comma,
 };
 
+/// Upper bound for `max_arity`, keeping the number of generated methods reasonable.
+pub const MAX_ARITY: usize = 16;
+
+/// What generated methods do when an enforced `timeout` expires, set with `timeout_action`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutAction {
+    /// Stop the run and return `None` without running the remaining steps.
+    None,
+    /// Panic with a message naming the step that was about to run.
+    Panic,
+    /// Print a message to stderr and keep running the remaining steps.
+    Log,
+}
+
+/// Additional method shapes selected with `mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineMode {
+    /// Generate `process3_iter`, returning a generated iterator type that runs one step per
+    /// `next` call.
+    Iterator,
+    /// Generate `process3_stepper`, returning a generated stepper type that runs one step per
+    /// `step` call and reports whether the run has finished.
+    Stepper,
+}
+
+/// How the generated methods chain their steps, set with `chain_type`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainType {
+    /// Unwrap each step's result like the `?` operator, stopping at the first `None` or `Err`,
+    /// through the same codegen for `Option` and `Result` fields.
+    Try,
+}
+
 /// Represents parsed attributes from the `#[pipeline(...)]` attribute.
 ///
-/// Supports recognized keys `skip` and `timeout` with typed values.
+/// Each recognized key has a typed field below; see the README for the full list.
 /// Unknown keys and optional values are preserved in `others`.
 #[derive(Clone, Default)]
 pub struct PipelineAttributes {
     /// If true, disables pipeline processing by skipping generation.
     pub skip: bool,
+    /// Optional `fn() -> bool` predicate checked when a run starts, skipping it if true.
+    pub skip_if: Option<Path>,
     /// Optional timeout value in milliseconds.
     pub timeout: Option<u64>,
+    /// Optional name of a struct field holding the timeout in milliseconds, instead of `timeout`.
+    pub timeout_field: Option<Ident>,
+    /// Optional action enforcing `timeout`; without it the timeout is only printed.
+    pub timeout_action: Option<TimeoutAction>,
+    /// Optional message the core methods panic with instead of returning `None`.
+    pub panic_on_none: Option<String>,
+    /// If true, `From<Option<T>>` (or `From<Result<T, E>>`) is implemented for the struct.
+    pub from_option: bool,
+    /// If true, a `{Struct}Config` builder and a `run` method taking it are generated.
+    pub config: bool,
+    /// If true, the generated impls are emitted inside an anonymous `const _: () = { .. };` block.
+    pub isolate: bool,
+    /// Optional additional method shape, see `PipelineMode`.
+    pub mode: Option<PipelineMode>,
+    /// Optional chaining strategy, see `ChainType`.
+    pub chain_type: Option<ChainType>,
+    /// Optional minimum number of steps; methods taking fewer steps are not generated.
+    pub min_steps: Option<usize>,
+    /// Optional highest arity of the generated `processN` methods (3 to 16, defaulting to 4).
+    pub max_arity: Option<usize>,
+    /// Optional naming scheme version for the generated methods (1 or 2, defaulting to 1).
+    pub version: Option<u32>,
+    /// Optional name of a trait to generate with the pipeline methods as default impls.
+    pub export_trait: Option<Ident>,
+    /// Optional name of an error enum to generate, returned by `try_process3`.
+    pub error_enum: Option<Ident>,
+    /// If true, generates `to_inner`/`from_inner` helpers (requires the `serde` feature).
+    pub serde: bool,
+    /// If true, generated methods take `&mut self` and store each successful step's value back.
+    pub mutate_field: bool,
+    /// If true, steps take and return `Cow<T>` values borrowed from the field.
+    pub cow: bool,
+    /// Optional inner type overriding the one extracted from the field's `Option<T>`.
+    pub inner: Option<Type>,
+    /// Optional where predicates replacing the `Clone` bounds added for the inner type.
+    pub clone_bound: Option<Vec<WherePredicate>>,
+    /// Extra where predicates added to the impls of the generated methods.
+    pub bound: Vec<WherePredicate>,
+    /// If true, the field's types are bound by `Copy` and the field is copied instead of cloned.
+    pub copy: bool,
+    /// If true, the core methods stop as soon as a step returns its input unchanged.
+    pub eq_check: bool,
+    /// If true, `processN_first_some` methods trying the steps as alternatives are generated.
+    pub first_some: bool,
+    /// If true, a `process3_take` moving the value out of the field is generated.
+    pub take: bool,
+    /// If true, consuming `into_*` variants are generated next to the borrowing methods.
+    pub both_receivers: bool,
+    /// If true, `Result` pipelines print the error of the failing step to stderr.
+    pub stop_on_err_log: bool,
+    /// Optional hook called with the failing step's index when a run returns `None`.
+    pub on_none: Option<Path>,
+    /// Optional sink called with a reference to the value produced by every successful step.
+    pub trace_to: Option<Path>,
+    /// Optional names of the step parameters `f1, f2, ...`, reported instead of step indices.
+    pub step_names: Option<Vec<String>>,
+    /// If true, a compile-time assertion that the struct is `Send` is generated.
+    pub assert_send: bool,
+    /// If true, `clear`/`set` helpers for the field are generated.
+    pub accessors: bool,
+    /// If true, a `peek` method borrowing the stored value is generated.
+    pub peek: bool,
+    /// If true, an `into_parts` method moving the stored value out is generated.
+    pub into_parts: bool,
+    /// If true, a `run_dyn` method running a `Vec` of boxed `FnMut` steps is generated.
+    pub run_dyn: bool,
+    /// If true, a `compose` method chaining two steps with another pipeline's closure is generated.
+    pub compose: bool,
+    /// If true, an `apply_all` method applying one step `n` times is generated.
+    pub apply_all: bool,
+    /// If true, a `process3_ok_or` method returning a caller-supplied error instead of `None` is
+    /// generated.
+    pub ok_or: bool,
+    /// If true, an `as_fn` method returning the two-step pipeline as a closure is generated.
+    pub as_fn: bool,
+    /// If true, a `with_step` method binding the first of two steps is generated.
+    pub with_step: bool,
+    /// If true, a `process_mixed` method pairing a fallible and an infallible step is generated.
+    pub process_mixed: bool,
+    /// If true, a `FIELD_NAME` associated constant naming the pipeline field is generated.
+    pub field_name: bool,
+    /// If true, runs are skipped when the deriving crate is built with `--cfg pipeline_force_skip`.
+    pub force_skip_cfg: bool,
+    /// If true, every step's value is passed through `std::hint::black_box`.
+    pub bench: bool,
+    /// If true, the methods become associated functions of a `{Struct}Pipeline` marker type.
+    pub namespace: bool,
+    /// Optional Cargo feature name under which every generated item is compiled.
+    pub feature_gate: Option<String>,
+    /// If true, the generated inherent impl blocks are marked `#[doc(hidden)]`.
+    pub doc_hidden: bool,
+    /// If true, the generated `process`/`processN` methods are marked `#[inline(always)]`.
+    pub inline_always: bool,
+    /// If true, a `Debug` impl showing only whether the field holds a value is generated.
+    pub derive_debug: bool,
+    /// If true, generates a rayon-based `process3_par_iter` (requires the `rayon` feature).
+    pub par_iter: bool,
+    /// If true, generates a `gen` block based `process3_gen` (requires the `nightly` feature).
+    pub generator: bool,
+    /// Optional path of this crate, under which the qualified attribute is also read.
+    pub crate_path: Option<Path>,
     /// Other unrecognized attribute key-value pairs.
     pub others: Vec<(Ident, Option<Expr>)>,
 }
@@ -25,7 +162,85 @@ impl fmt::Debug for PipelineAttributes {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PipelineAttributes")
             .field("skip", &self.skip)
+            .field(
+                "skip_if",
+                &self.skip_if.as_ref().map(|path| path.to_token_stream()),
+            )
             .field("timeout", &self.timeout)
+            .field("timeout_field", &self.timeout_field)
+            .field("timeout_action", &self.timeout_action)
+            .field("panic_on_none", &self.panic_on_none)
+            .field("from_option", &self.from_option)
+            .field("config", &self.config)
+            .field("isolate", &self.isolate)
+            .field("mode", &self.mode)
+            .field("chain_type", &self.chain_type)
+            .field("min_steps", &self.min_steps)
+            .field("max_arity", &self.max_arity)
+            .field("version", &self.version)
+            .field("export_trait", &self.export_trait)
+            .field("error_enum", &self.error_enum)
+            .field("serde", &self.serde)
+            .field("mutate_field", &self.mutate_field)
+            .field("cow", &self.cow)
+            .field("both_receivers", &self.both_receivers)
+            .field("take", &self.take)
+            .field("first_some", &self.first_some)
+            .field("inner", &self.inner.as_ref().map(|ty| ty.to_token_stream()))
+            .field(
+                "clone_bound",
+                &self.clone_bound.as_ref().map(|predicates| {
+                    predicates
+                        .iter()
+                        .map(|predicate| predicate.to_token_stream())
+                        .collect::<Vec<_>>()
+                }),
+            )
+            .field(
+                "bound",
+                &self
+                    .bound
+                    .iter()
+                    .map(|predicate| predicate.to_token_stream())
+                    .collect::<Vec<_>>(),
+            )
+            .field("copy", &self.copy)
+            .field("eq_check", &self.eq_check)
+            .field("stop_on_err_log", &self.stop_on_err_log)
+            .field(
+                "on_none",
+                &self.on_none.as_ref().map(|path| path.to_token_stream()),
+            )
+            .field(
+                "trace_to",
+                &self.trace_to.as_ref().map(|path| path.to_token_stream()),
+            )
+            .field("step_names", &self.step_names)
+            .field("assert_send", &self.assert_send)
+            .field("accessors", &self.accessors)
+            .field("peek", &self.peek)
+            .field("into_parts", &self.into_parts)
+            .field("run_dyn", &self.run_dyn)
+            .field("compose", &self.compose)
+            .field("apply_all", &self.apply_all)
+            .field("ok_or", &self.ok_or)
+            .field("as_fn", &self.as_fn)
+            .field("with_step", &self.with_step)
+            .field("process_mixed", &self.process_mixed)
+            .field("field_name", &self.field_name)
+            .field("force_skip_cfg", &self.force_skip_cfg)
+            .field("bench", &self.bench)
+            .field("namespace", &self.namespace)
+            .field("feature_gate", &self.feature_gate)
+            .field("doc_hidden", &self.doc_hidden)
+            .field("inline_always", &self.inline_always)
+            .field("derive_debug", &self.derive_debug)
+            .field("par_iter", &self.par_iter)
+            .field("generator", &self.generator)
+            .field(
+                "crate_path",
+                &self.crate_path.as_ref().map(|path| path.to_token_stream()),
+            )
             .field(
                 "others",
                 &self
@@ -46,7 +261,8 @@ impl fmt::Debug for PipelineAttributes {
 
 /// Parses comma-separated key-value pairs inside `#[pipeline(...)]` attribute.
 ///
-/// Recognizes `skip` (boolean) and `timeout` (integer) keys specially.
+/// Recognized keys are validated and converted to their typed fields, see
+/// `PipelineAttributes::apply`.
 /// Unknown keys are collected as `others`.
 impl Parse for PipelineAttributes {
     fn parse(input: ParseStream) -> Result<Self> {
@@ -54,75 +270,456 @@ impl Parse for PipelineAttributes {
 
         // Parse comma-separated pairs like `key` or `key = value`
         let pairs = Punctuated::<PipelineAttributePair, Comma>::parse_terminated(input)?;
+        attrs.merge_pairs(pairs, &mut Vec::new())?;
 
+        Ok(attrs)
+    }
+}
+
+impl PipelineAttributes {
+    /// Applies the pairs of one `#[pipeline(...)]` attribute on top of the current values.
+    ///
+    /// `seen` holds the keys set by previously merged attributes; setting a key twice is an error.
+    fn merge_pairs(
+        &mut self,
+        pairs: impl IntoIterator<Item = PipelineAttributePair>,
+        seen: &mut Vec<Ident>,
+    ) -> Result<()> {
         for pair in pairs {
-            let key_str = pair.key.to_string();
-            match key_str.as_str() {
-                "skip" => {
-                    // Accept either bare `skip` (imply true) or skip = true/false
-                    attrs.skip = if let Some(expr) = pair.value {
-                        match expr {
-                            Expr::Lit(lit) => match &lit.lit {
-                                syn::Lit::Bool(b) => b.value,
-                                other => {
-                                    return Err(syn::Error::new_spanned(
-                                        other,
-                                        "Expected boolean literal for 'skip'",
-                                    ));
-                                }
-                            },
-                            other => {
-                                return Err(syn::Error::new_spanned(
-                                    other,
-                                    "Expected boolean literal for 'skip'",
-                                ));
-                            }
-                        }
-                    } else {
-                        true // bare `skip` means true
-                    };
-                }
-                "timeout" => {
-                    if let Some(expr) = pair.value {
-                        match expr {
-                            Expr::Lit(lit) => match &lit.lit {
-                                syn::Lit::Int(int_lit) => {
-                                    attrs.timeout = Some(int_lit.base10_parse()?);
-                                }
-                                other => {
-                                    return Err(syn::Error::new_spanned(
-                                        other,
-                                        "Expected integer literal for 'timeout'",
-                                    ));
-                                }
-                            },
-                            other => {
+            if seen.contains(&pair.key) {
+                return Err(syn::Error::new_spanned(
+                    &pair.key,
+                    format!("Duplicate pipeline attribute key '{}'", pair.key),
+                ));
+            }
+            seen.push(pair.key.clone());
+            self.apply(pair)?;
+        }
+        Ok(())
+    }
+
+    /// Applies a single key-value pair, validating the value for recognized keys.
+    fn apply(&mut self, pair: PipelineAttributePair) -> Result<()> {
+        let key_str = pair.key.to_string();
+        match key_str.as_str() {
+            "skip" => {
+                // Generated configurations may write the flag as `skip = 0` or `skip = 1`
+                self.skip = match &pair.value {
+                    Some(Expr::Lit(lit)) => match &lit.lit {
+                        syn::Lit::Int(int_lit) => match int_lit.base10_digits() {
+                            "0" => false,
+                            "1" => true,
+                            _ => {
                                 return Err(syn::Error::new_spanned(
-                                    other,
-                                    "Expected integer literal for 'timeout'",
+                                    int_lit,
+                                    "Expected boolean literal, `0` or `1` for 'skip'",
                                 ));
                             }
-                        }
-                    } else {
+                        },
+                        _ => parse_flag(&pair)?,
+                    },
+                    _ => parse_flag(&pair)?,
+                };
+            }
+            "skip_if" => {
+                self.skip_if = Some(parse_path(&pair, "skip_if = pipelines_disabled")?);
+            }
+            "timeout" => {
+                self.timeout = Some(parse_int(&pair, "timeout = 1000")?);
+            }
+            "timeout_field" => {
+                self.timeout_field =
+                    Some(parse_str(&pair, "timeout_field = \"deadline_ms\"")?.parse()?);
+            }
+            "timeout_action" => {
+                let action = parse_str(&pair, "timeout_action = \"none\"")?;
+                self.timeout_action = Some(match action.value().as_str() {
+                    "none" => TimeoutAction::None,
+                    "panic" => TimeoutAction::Panic,
+                    "log" => TimeoutAction::Log,
+                    _ => {
                         return Err(syn::Error::new_spanned(
-                            pair.key,
-                            "'timeout' attribute requires an integer value, e.g. timeout = 1000",
+                            action,
+                            "Unknown 'timeout_action', expected `none`, `panic` or `log`",
                         ));
                     }
+                });
+            }
+            "panic_on_none" => {
+                // A bare flag panics with a default message, a string replaces it
+                self.panic_on_none = Some(match pair.value {
+                    None => String::from("pipeline produced None"),
+                    Some(_) => parse_str(&pair, "panic_on_none = \"config must load\"")?.value(),
+                });
+            }
+            "from_option" => {
+                self.from_option = parse_flag(&pair)?;
+            }
+            "config" => {
+                self.config = parse_flag(&pair)?;
+            }
+            "isolate" => {
+                self.isolate = parse_flag(&pair)?;
+            }
+            "mode" => {
+                let mode = parse_str(&pair, "mode = \"iterator\"")?;
+                self.mode = Some(match mode.value().as_str() {
+                    "iterator" => PipelineMode::Iterator,
+                    "stepper" => PipelineMode::Stepper,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            mode,
+                            "Unknown 'mode', expected `iterator` or `stepper`",
+                        ));
+                    }
+                });
+            }
+            "chain_type" => {
+                let chain_type = parse_str(&pair, "chain_type = \"try\"")?;
+                self.chain_type = Some(match chain_type.value().as_str() {
+                    "try" => ChainType::Try,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            chain_type,
+                            "Unknown 'chain_type', expected `try`",
+                        ));
+                    }
+                });
+            }
+            "min_steps" => {
+                let min_steps = parse_int(&pair, "min_steps = 2")?;
+                // The longest possible pipeline, `process16`, takes fifteen steps; the bound
+                // against the configured `max_arity` is checked once all attributes are merged
+                if !(1..=MAX_ARITY - 1).contains(&min_steps) {
+                    return Err(syn::Error::new_spanned(
+                        pair.value,
+                        format!("'min_steps' must be between 1 and {}", MAX_ARITY - 1),
+                    ));
+                }
+                self.min_steps = Some(min_steps);
+            }
+            "max_arity" => {
+                let max_arity = parse_int(&pair, "max_arity = 6")?;
+                if !(3..=MAX_ARITY).contains(&max_arity) {
+                    return Err(syn::Error::new_spanned(
+                        pair.value,
+                        format!("'max_arity' must be between 3 and {}", MAX_ARITY),
+                    ));
                 }
-                _ => {
-                    // Optional: warn about unknown keys but still collect them
-                    let _warn = syn::Error::new_spanned(
-                        pair.key.clone(),
-                        format!("Unknown pipeline attribute key '{}'", key_str),
-                    );
-                    // Store unknown attribute key-value pair anyway
-                    attrs.others.push((pair.key, pair.value));
+                self.max_arity = Some(max_arity);
+            }
+            "version" => {
+                let version = parse_int(&pair, "version = 2")?;
+                if !(1..=2).contains(&version) {
+                    return Err(syn::Error::new_spanned(
+                        pair.value,
+                        "Unsupported pipeline 'version', expected 1 or 2",
+                    ));
+                }
+                self.version = Some(version);
+            }
+            "serde" => {
+                self.serde = parse_flag(&pair)?;
+                if self.serde && !cfg!(feature = "serde") {
+                    return Err(syn::Error::new_spanned(
+                        pair.key,
+                        "'serde' attribute requires the `serde` feature of pipeline_derive",
+                    ));
+                }
+            }
+            "par_iter" => {
+                self.par_iter = parse_flag(&pair)?;
+                if self.par_iter && !cfg!(feature = "rayon") {
+                    return Err(syn::Error::new_spanned(
+                        pair.key,
+                        "'par_iter' attribute requires the `rayon` feature of pipeline_derive",
+                    ));
+                }
+            }
+            "generator" => {
+                self.generator = parse_flag(&pair)?;
+                if self.generator && !cfg!(feature = "nightly") {
+                    return Err(syn::Error::new_spanned(
+                        pair.key,
+                        "'generator' attribute requires the `nightly` feature of pipeline_derive",
+                    ));
+                }
+            }
+            "mutate_field" => {
+                self.mutate_field = parse_flag(&pair)?;
+            }
+            "cow" => {
+                self.cow = parse_flag(&pair)?;
+            }
+            "inner" => {
+                self.inner = Some(parse_str(&pair, "inner = \"MyType\"")?.parse()?);
+            }
+            "copy" => {
+                self.copy = parse_flag(&pair)?;
+            }
+            "eq_check" => {
+                self.eq_check = parse_flag(&pair)?;
+            }
+            "clone_bound" => {
+                let predicates = parse_str(&pair, "clone_bound = \"T: Clone + Send\"")?
+                    .parse_with(Punctuated::<WherePredicate, Comma>::parse_terminated)?;
+                self.clone_bound = Some(predicates.into_iter().collect());
+            }
+            "bound" => {
+                let predicates = parse_str(&pair, "bound = \"T: Send + 'static\"")?
+                    .parse_with(Punctuated::<WherePredicate, Comma>::parse_terminated)?;
+                self.bound = predicates.into_iter().collect();
+            }
+            "both_receivers" => {
+                self.both_receivers = parse_flag(&pair)?;
+            }
+            "first_some" => {
+                self.first_some = parse_flag(&pair)?;
+            }
+            "take" => {
+                self.take = parse_flag(&pair)?;
+            }
+            "stop_on_err_log" => {
+                self.stop_on_err_log = parse_flag(&pair)?;
+            }
+            "on_none" => {
+                self.on_none = Some(parse_path(&pair, "on_none = log_failure")?);
+            }
+            "assert_send" => {
+                self.assert_send = parse_flag(&pair)?;
+            }
+            "accessors" => {
+                self.accessors = parse_flag(&pair)?;
+            }
+            "peek" => {
+                self.peek = parse_flag(&pair)?;
+            }
+            "into_parts" => {
+                self.into_parts = parse_flag(&pair)?;
+            }
+            "run_dyn" => {
+                self.run_dyn = parse_flag(&pair)?;
+            }
+            "compose" => {
+                self.compose = parse_flag(&pair)?;
+            }
+            "apply_all" => {
+                self.apply_all = parse_flag(&pair)?;
+            }
+            "ok_or" => {
+                self.ok_or = parse_flag(&pair)?;
+            }
+            "as_fn" => {
+                self.as_fn = parse_flag(&pair)?;
+            }
+            "with_step" => {
+                self.with_step = parse_flag(&pair)?;
+            }
+            "process_mixed" => {
+                self.process_mixed = parse_flag(&pair)?;
+            }
+            "field_name" => {
+                self.field_name = parse_flag(&pair)?;
+            }
+            "force_skip_cfg" => {
+                self.force_skip_cfg = parse_flag(&pair)?;
+            }
+            "bench" => {
+                self.bench = parse_flag(&pair)?;
+            }
+            "namespace" => {
+                self.namespace = parse_flag(&pair)?;
+            }
+            "feature_gate" => {
+                let feature = parse_str(&pair, "feature_gate = \"pipelines\"")?;
+                if feature.value().is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        &feature,
+                        "'feature_gate' must name a feature",
+                    ));
                 }
+                self.feature_gate = Some(feature.value());
+            }
+            "doc_hidden" => {
+                self.doc_hidden = parse_flag(&pair)?;
+            }
+            "inline_always" => {
+                self.inline_always = parse_flag(&pair)?;
+            }
+            "derive_debug" => {
+                self.derive_debug = parse_flag(&pair)?;
+            }
+            "trace_to" => {
+                self.trace_to = Some(parse_path(&pair, "trace_to = record_value")?);
+            }
+            "step_names" => {
+                let names = parse_str_list(&pair, "step_names = [\"parse\", \"validate\"]")?;
+                self.step_names = Some(names.iter().map(syn::LitStr::value).collect());
+            }
+            "error_enum" => {
+                self.error_enum =
+                    Some(parse_str(&pair, "error_enum = \"PipelineError\"")?.parse()?);
+            }
+            "export_trait" => {
+                self.export_trait =
+                    Some(parse_str(&pair, "export_trait = \"MyPipeline\"")?.parse()?);
+            }
+            "crate" => {
+                self.crate_path = Some(parse_str(&pair, "crate = \"pipeline\"")?.parse()?);
+            }
+            _ => {
+                // Optional: warn about unknown keys but still collect them
+                let _warn = syn::Error::new_spanned(
+                    pair.key.clone(),
+                    format!("Unknown pipeline attribute key '{}'", key_str),
+                );
+                // Store unknown attribute key-value pair anyway
+                self.others.push((pair.key, pair.value));
             }
         }
+        Ok(())
+    }
+}
 
-        Ok(attrs)
+/// Parses a boolean flag such as `skip`.
+///
+/// Accepts either the bare key (implying true) or `key = true/false`.
+fn parse_flag(pair: &PipelineAttributePair) -> Result<bool> {
+    match &pair.value {
+        Some(Expr::Lit(lit)) => match &lit.lit {
+            syn::Lit::Bool(b) => Ok(b.value),
+            other => Err(syn::Error::new_spanned(
+                other,
+                format!("Expected boolean literal for '{}'", pair.key),
+            )),
+        },
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            format!("Expected boolean literal for '{}'", pair.key),
+        )),
+        None => Ok(true), // bare key means true
+    }
+}
+
+/// Parses an integer value such as `timeout = 1000`.
+///
+/// `example` is shown in the error when the value is missing, negative or an expression.
+fn parse_int<N>(pair: &PipelineAttributePair, example: &str) -> Result<N>
+where
+    N: std::str::FromStr,
+    N::Err: fmt::Display,
+{
+    match &pair.value {
+        Some(Expr::Lit(lit)) => match &lit.lit {
+            syn::Lit::Int(int_lit) => int_lit.base10_parse(),
+            other => Err(syn::Error::new_spanned(
+                other,
+                format!("Expected integer literal for '{}'", pair.key),
+            )),
+        },
+        // Negative numbers and arithmetic parse as expressions; name the problem rather than
+        // just the expected token
+        Some(Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        })) if matches!(**expr, Expr::Lit(_)) => Err(syn::Error::new_spanned(
+            &pair.value,
+            format!(
+                "'{}' must be a non-negative integer literal, e.g. {}",
+                pair.key, example
+            ),
+        )),
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            format!(
+                "'{}' must be a non-negative integer literal, not an expression, e.g. {}",
+                pair.key, example
+            ),
+        )),
+        None => Err(syn::Error::new_spanned(
+            &pair.key,
+            format!(
+                "'{}' attribute requires an integer value, e.g. {}",
+                pair.key, example
+            ),
+        )),
+    }
+}
+
+/// Parses a path value such as `on_none = path::to::fn`.
+///
+/// `example` is shown in the error when the value is missing.
+fn parse_path(pair: &PipelineAttributePair, example: &str) -> Result<Path> {
+    match &pair.value {
+        Some(Expr::Path(expr_path)) if expr_path.qself.is_none() => Ok(expr_path.path.clone()),
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            format!("Expected path for '{}'", pair.key),
+        )),
+        None => Err(syn::Error::new_spanned(
+            &pair.key,
+            format!("'{}' attribute requires a path, e.g. {}", pair.key, example),
+        )),
+    }
+}
+
+/// Parses a string literal value such as `export_trait = "MyPipeline"`.
+///
+/// `example` is shown in the error when the value is missing.
+fn parse_str(pair: &PipelineAttributePair, example: &str) -> Result<syn::LitStr> {
+    match &pair.value {
+        Some(Expr::Lit(lit)) => match &lit.lit {
+            syn::Lit::Str(str_lit) => Ok(str_lit.clone()),
+            other => Err(syn::Error::new_spanned(
+                other,
+                format!("Expected string literal for '{}'", pair.key),
+            )),
+        },
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            format!("Expected string literal for '{}'", pair.key),
+        )),
+        None => Err(syn::Error::new_spanned(
+            &pair.key,
+            format!(
+                "'{}' attribute requires a value, e.g. {}",
+                pair.key, example
+            ),
+        )),
+    }
+}
+
+/// Parses a list of string literals such as `step_names = ["parse", "validate"]`.
+///
+/// `example` is shown in the error when the value is missing.
+fn parse_str_list(pair: &PipelineAttributePair, example: &str) -> Result<Vec<syn::LitStr>> {
+    match &pair.value {
+        Some(Expr::Array(array)) => array
+            .elems
+            .iter()
+            .map(|elem| match elem {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(str_lit),
+                    ..
+                }) => Ok(str_lit.clone()),
+                other => Err(syn::Error::new_spanned(
+                    other,
+                    format!("Expected string literal in '{}'", pair.key),
+                )),
+            })
+            .collect(),
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            format!("Expected a list of string literals for '{}'", pair.key),
+        )),
+        None => Err(syn::Error::new_spanned(
+            &pair.key,
+            format!(
+                "'{}' attribute requires a list of names, e.g. {}",
+                pair.key, example
+            ),
+        )),
     }
 }
 
@@ -136,10 +733,22 @@ struct PipelineAttributePair {
 
 impl Parse for PipelineAttributePair {
     fn parse(input: ParseStream) -> Result<Self> {
-        let key: Ident = input.parse()?;
+        // `crate` is a keyword, so it is not parsed as an identifier
+        let key: Ident = if input.peek(Token![crate]) {
+            let token: Token![crate] = input.parse()?;
+            Ident::new("crate", token.span)
+        } else {
+            input.parse()?
+        };
         let value = if input.peek(Token![=]) {
             input.parse::<Token![=]>()?;
-            Some(input.parse()?)
+            // Values passed into a `macro_rules!` macro as `$value:literal` or `$value:expr`
+            // arrive wrapped in an invisible group
+            let mut value: Expr = input.parse()?;
+            while let Expr::Group(group) = value {
+                value = *group.expr;
+            }
+            Some(value)
         } else {
             None
         };
@@ -147,14 +756,125 @@ impl Parse for PipelineAttributePair {
     }
 }
 
-/// Parse the `#[pipeline(...)]` attribute from a struct's attributes.
+/// Environment variable providing a crate-wide default for `timeout`, read at expansion time.
+const DEFAULT_TIMEOUT_ENV: &str = "PIPELINE_DEFAULT_TIMEOUT";
+
+/// Segments of `path`, ignoring a leading `::`, for comparing crate paths.
+fn segments(path: &Path) -> Vec<String> {
+    path.segments.iter().map(|s| s.ident.to_string()).collect()
+}
+
+/// The crate path of a qualified `#[krate::pipeline(...)]`, i.e. its path without the last
+/// segment, or `None` for other attributes.
+fn qualifier(attr: &syn::Attribute) -> Option<Vec<String>> {
+    let mut path = segments(attr.path());
+    (path.len() > 1 && path.pop()? == "pipeline").then_some(path)
+}
+
+/// The path declared with `crate = "..."` in the arguments of a pipeline attribute, if any.
+///
+/// Malformed arguments declare nothing here; they are reported when the attributes are parsed.
+pub fn declared_crate_path(args: TokenStream) -> Option<Vec<String>> {
+    let pairs = Punctuated::<PipelineAttributePair, Comma>::parse_terminated
+        .parse2(args)
+        .ok()?;
+    let pair = pairs.into_iter().find(|pair| pair.key == "crate")?;
+    Some(segments(&parse_str(&pair, "").ok()?.parse().ok()?))
+}
+
+/// The paths under which the qualified attribute is read: `pipeline_derive` itself, and the
+/// path declared with `crate = "..."` for a renamed or re-exported dependency, e.g.
+/// `#[pd::pipeline(crate = "pd", ...)]`.
+///
+/// A qualified attribute only declares its own path, so another crate's `pipeline` attribute
+/// is not read by accident.
+pub fn crate_paths(attrs: &[syn::Attribute]) -> Vec<Vec<String>> {
+    let mut paths = vec![vec!["pipeline_derive".to_string()]];
+    for attr in attrs {
+        let qualifier = qualifier(attr);
+        let bare = attr.path().leading_colon.is_none() && attr.path().is_ident("pipeline");
+        if !bare && qualifier.is_none() {
+            continue;
+        }
+        let Ok(list) = attr.meta.require_list() else {
+            continue;
+        };
+        if let Some(path) = declared_crate_path(list.tokens.clone()) {
+            if bare || qualifier.as_ref() == Some(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+/// Whether `attr` is a pipeline attribute: the derive's `#[pipeline(...)]` helper or the
+/// qualified form, which stays unambiguous when another crate's `pipeline` attribute is in
+/// scope.
+fn is_pipeline_attr(attr: &syn::Attribute, crate_paths: &[Vec<String>]) -> bool {
+    let path = attr.path();
+    path.leading_colon.is_none() && path.is_ident("pipeline")
+        || is_qualified_pipeline_attr(attr, crate_paths)
+}
+
+/// Whether `attr` is the qualified `#[pipeline_derive::pipeline(...)]`, with or without a
+/// leading `::`, or its equivalent under one of the other `crate_paths`.
+pub fn is_qualified_pipeline_attr(attr: &syn::Attribute, crate_paths: &[Vec<String>]) -> bool {
+    qualifier(attr).is_some_and(|path| crate_paths.contains(&path))
+}
+
+/// Parse the `#[pipeline(...)]` attributes from a struct's attributes.
 ///
 /// Returns parsed `PipelineAttributes` or default if attribute not present.
+/// Several `#[pipeline(...)]` attributes are merged into one configuration; a key set by more
+/// than one of them is an error. A bare `#[pipeline]` without arguments contributes nothing.
+/// `#[pipeline_derive::pipeline(...)]` is read the same way as `#[pipeline(...)]`, as is the
+/// qualified form under a path declared with `crate`.
+/// Field attributes of other derives are ignored; `#[pipeline(...)]` on a field is an error.
+/// Without an explicit `timeout` or `timeout_field`, the value of `PIPELINE_DEFAULT_TIMEOUT` is
+/// used if set.
 pub fn parse_attributes(input: &syn::DeriveInput) -> Result<PipelineAttributes> {
+    let mut attrs = PipelineAttributes::default();
+    let mut seen = Vec::new();
+    let crate_paths = crate_paths(&input.attrs);
     for attr in &input.attrs {
-        if attr.path().is_ident("pipeline") {
-            return attr.parse_args();
+        if is_pipeline_attr(attr, &crate_paths) {
+            // Bare `#[pipeline]` carries no arguments, so it leaves the defaults untouched
+            if let syn::Meta::Path(_) = attr.meta {
+                continue;
+            }
+            let pairs =
+                attr.parse_args_with(Punctuated::<PipelineAttributePair, Comma>::parse_terminated)?;
+            attrs.merge_pairs(pairs, &mut seen)?;
+        }
+    }
+    // Attributes of other derives on the fields are left alone, but a field-level
+    // `#[pipeline(...)]` would otherwise be accepted by the compiler and silently ignored
+    if let syn::Data::Struct(data) = &input.data {
+        let field_attr = data
+            .fields
+            .iter()
+            .flat_map(|field| &field.attrs)
+            .find(|attr| is_pipeline_attr(attr, &crate_paths));
+        if let Some(attr) = field_attr {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "Pipeline attributes belong on the struct, not on its fields",
+            ));
+        }
+    }
+    if attrs.timeout.is_none() && attrs.timeout_field.is_none() {
+        if let Ok(timeout) = std::env::var(DEFAULT_TIMEOUT_ENV) {
+            attrs.timeout = Some(timeout.trim().parse().map_err(|_| {
+                syn::Error::new_spanned(
+                    &input.ident,
+                    format!(
+                        "{} must be a timeout in milliseconds, found '{}'",
+                        DEFAULT_TIMEOUT_ENV, timeout
+                    ),
+                )
+            })?);
         }
     }
-    Ok(PipelineAttributes::default())
+    Ok(attrs)
 }
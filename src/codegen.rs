@@ -1,7 +1,8 @@
 use crate::attributes::{self, PipelineAttributes};
 use crate::errors::Result;
 use proc_macro2::TokenStream;
-use syn::DeriveInput;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{DeriveInput, Ident, ext::IdentExt};
 
 use crate::pipeline;
 
@@ -15,3 +16,71 @@ pub fn pipeline_derive(input: DeriveInput) -> Result<TokenStream> {
     // Delegate to main pipeline derive logic with parsed attributes
     pipeline::pipeline_derive(input, &attrs)
 }
+
+/// Name of the hidden trait through which the derive confirms that it read the qualified
+/// `#[pipeline_derive::pipeline(...)]` attributes of `struct_name`.
+fn derive_marker(struct_name: &Ident) -> Ident {
+    format_ident!(
+        "{}PipelineDerived",
+        struct_name.unraw(),
+        span = struct_name.span()
+    )
+}
+
+/// Implements the marker declared by the qualified attribute, if the struct carries one.
+///
+/// It is emitted even when the derive fails, so the failure is not also reported as an unread
+/// attribute, and without `feature_gate`, which cannot remove the attribute's check.
+pub fn qualified_marker_impl(input: &DeriveInput) -> TokenStream {
+    let crate_paths = attributes::crate_paths(&input.attrs);
+    if !input
+        .attrs
+        .iter()
+        .any(|attr| attributes::is_qualified_pipeline_attr(attr, &crate_paths))
+    {
+        return quote! {};
+    }
+    let marker = derive_marker(&input.ident);
+    quote_spanned! { input.ident.span()=>
+        impl #marker for () {}
+    }
+}
+
+/// Expands the qualified `#[pipeline_derive::pipeline(...)]` attribute.
+///
+/// The struct is left unchanged; the options are read by `#[derive(Pipeline)]`. As the attribute
+/// cannot see whether a derive read it, it declares a marker trait that only the derive
+/// implements, and fails to compile without that impl, e.g. when the derive is missing or
+/// written below the attribute. Of several qualified attributes, the last one declares it.
+pub fn qualified_attribute(args: TokenStream, input: &DeriveInput) -> TokenStream {
+    let mut crate_paths = attributes::crate_paths(&input.attrs);
+    crate_paths.extend(attributes::declared_crate_path(args));
+    if input
+        .attrs
+        .iter()
+        .any(|attr| attributes::is_qualified_pipeline_attr(attr, &crate_paths))
+    {
+        return quote! {};
+    }
+    let struct_name = &input.ident;
+    let marker = derive_marker(struct_name);
+    let message = format!(
+        "the qualified `pipeline` attribute on `{}` is not read by `#[derive(Pipeline)]`",
+        struct_name.unraw()
+    );
+    quote_spanned! { struct_name.span()=>
+        #[doc(hidden)]
+        #[diagnostic::on_unimplemented(
+            message = #message,
+            label = "the options of this struct's qualified attribute are ignored",
+            note = "write the attribute below `#[derive(Pipeline)]`, which only reads the attributes after it",
+            note = "under a renamed or re-exported dependency, declare its path with `crate = \"..\"`"
+        )]
+        trait #marker {}
+
+        const _: fn() = {
+            fn derived<T: #marker>() {}
+            derived::<()>
+        };
+    }
+}
@@ -0,0 +1,1032 @@
+use crate::attributes::{PipelineAttributes, TimeoutAction};
+use crate::errors::{Error, Result};
+use crate::pipeline::{
+    FieldKind, extract_inner_type, mentions_self, shared_value_type, step_number, ungroup,
+    vec_element_type,
+};
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote, quote_spanned};
+use syn::{
+    DeriveInput, GenericParam, Generics, Ident, Type, TypePath, WherePredicate, ext::IdentExt,
+    parse_quote_spanned, spanned::Spanned,
+};
+
+/// What the generators of the pipeline methods share: the pipeline field, the bounded generics
+/// and the pieces of method bodies configured by the attributes.
+///
+/// `Context::new` validates the struct and its attributes up front, so generating the methods
+/// themselves cannot fail.
+pub struct Context<'a> {
+    pub attrs: &'a PipelineAttributes,
+    pub input: &'a DeriveInput,
+    pub struct_name: &'a Ident,
+    /// Name of the pipeline field.
+    pub field_ident: &'a Ident,
+    /// `T` of the `Option<T>` or `Result<T, E>` field.
+    pub inner_type: &'a Type,
+    /// Whether the field is an `Option` or a `Result`.
+    pub kind: FieldKind<'a>,
+    /// Whether the field is a shared `Arc<Mutex<..>>`, accessed through its lock.
+    pub shared: bool,
+    /// What the method bodies read the struct through: `self`, or `pipeline` with `namespace`.
+    pub this: TokenStream,
+    /// Binds `self` to `pipeline` in the impls that stay on the struct under `namespace`.
+    pub rebind: TokenStream,
+    /// Receiver of the impls that stay on the struct, quoted like `this` so the bodies' `self`
+    /// resolves even when the struct's name comes from a `macro_rules!` caller.
+    pub self_receiver: TokenStream,
+    /// The `{Struct}Pipeline` marker type declared with `namespace`.
+    pub marker: Ident,
+    /// The field as a place expression, for writing it back.
+    pub field_place: TokenStream,
+    /// The struct's generics with the `Clone` (or `Copy`), `bound` and `eq_check` predicates.
+    pub generics: Generics,
+    /// The struct's own generic parameters, for `use<..>` bounds of returned `impl Trait` values
+    /// that never touch `self`, so they can outlive the borrow.
+    pub struct_params: Vec<TokenStream>,
+    /// Wrapper type returned by steps and methods, e.g. `Option<T>`.
+    pub output: TokenStream,
+    /// Doc lines noting that `skip` is set, empty otherwise.
+    pub skip_doc: TokenStream,
+    /// Prints the configured timeout when a method is called.
+    pub timeout_code: TokenStream,
+    /// Timeout in ms enforced by `timeout_action`, and what happens once it expires.
+    pub deadline: Option<(TokenStream, TimeoutAction)>,
+    /// Value passed between steps: `T`, or `Cow<T>` with `cow`.
+    pub item: TokenStream,
+    /// The `'__pipeline` lifetime `cow` borrows the field for, if any.
+    pub item_lifetime: TokenStream,
+    /// Wrapper of `item` returned by steps, e.g. `Option<T>`.
+    pub step_output: TokenStream,
+    /// `inner_type` as tokens, for chains passing plain `T` values even with `cow`.
+    pub inner_item: TokenStream,
+    /// Constructor of a successful value, `Some` or `Ok`.
+    pub success: TokenStream,
+    /// Stores a step's value in the field with `mutate_field`.
+    pub after_step: TokenStream,
+    /// Name of the hidden trait calling the `on_none` hook.
+    pub on_none_trait: Ident,
+    /// `E: Debug` with `stop_on_err_log`, so failing steps can be printed.
+    pub log_bounds: Vec<WherePredicate>,
+    /// Hands a step's `item` value to the `trace_to` sink.
+    pub trace_step: TokenStream,
+    /// Hands a step's plain `T` value to the `trace_to` sink, for chains over `inner_item`.
+    pub trace_owned: TokenStream,
+    /// Arities of the generated `processN` methods, from 3 up to `max_arity`.
+    pub arities: Vec<usize>,
+    /// Element type of the `Vec` that `par_iter` runs the steps over, if set.
+    pub par_iter_element: Option<&'a Type>,
+    /// Whether the core methods are `processN` (version 1) rather than `process` (version 2).
+    pub fixed_arity: bool,
+    /// Fewest steps a generated method may take, from `min_steps`.
+    pub min_steps: usize,
+    /// `#[doc(hidden)]` with `doc_hidden`.
+    pub hidden: TokenStream,
+    /// `#[inline(always)]` with `inline_always`.
+    pub inline: TokenStream,
+    /// Return type of the core methods: `T` with `panic_on_none`, otherwise `step_output`.
+    pub core_output: TokenStream,
+    /// The `# Panics` section of the core methods with `panic_on_none`.
+    pub core_panics: TokenStream,
+}
+
+impl<'a> Context<'a> {
+    /// Validates the struct and its attributes, and works out the pieces the generated methods
+    /// are assembled from.
+    ///
+    /// # Errors
+    /// Returns the errors documented on `pipeline::pipeline_derive`.
+    pub fn new(input: &'a DeriveInput, attrs: &'a PipelineAttributes) -> Result<Self> {
+        let struct_name = &input.ident;
+
+        // Validate that the struct has exactly one named field
+        let field = if let syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) = &input.data
+        {
+            // The field named by `timeout_field` holds configuration, not the pipeline value
+            if let Some(timeout_field) = &attrs.timeout_field {
+                if !fields
+                    .named
+                    .iter()
+                    .any(|field| field.ident.as_ref() == Some(timeout_field))
+                {
+                    return Err(Error::spanned(
+                        timeout_field,
+                        format!(
+                            "'timeout_field' names no field `{}` of the struct",
+                            timeout_field
+                        ),
+                    ));
+                }
+            }
+            let pipeline_fields: Vec<_> = fields
+                .named
+                .iter()
+                .filter(|field| attrs.timeout_field.is_none() || field.ident != attrs.timeout_field)
+                .collect();
+            if pipeline_fields.len() == 1 {
+                pipeline_fields[0]
+            } else {
+                let err = Error::spanned(
+                    &input.ident,
+                    "Expected a struct with exactly one named field",
+                );
+                // Point at every field beyond the first so the user sees what to remove
+                // A field whose `cfg` is false was removed before the derive ran
+                let err = if pipeline_fields.is_empty() {
+                    err.span_help(fields.span(), "add a single field of type Option<T>")
+                        .span_help(
+                            fields.span(),
+                            "if the field is behind `#[cfg(..)]`, gate the derive on the same condition with `#[cfg_attr(.., derive(Pipeline))]`",
+                        )
+                } else {
+                    pipeline_fields.iter().skip(1).fold(err, |err, extra| {
+                        let name = extra
+                            .ident
+                            .as_ref()
+                            .map_or_else(String::new, |ident| format!(" `{}`", ident));
+                        err.span_help(extra.span(), format!("extra field{} declared here", name))
+                    })
+                };
+                return Err(err);
+            }
+        } else {
+            return Err(Error::spanned(
+                &input.ident,
+                "Expected a struct with named fields",
+            ));
+        };
+
+        // Extract the identifier of the single named field
+        let field_ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| Error::spanned(field, "Expected named field with identifier"))?;
+
+        // Extract inner type T (and E for Result<T, E>) from the field's type, unless the user
+        // declared it with `inner`, in which case the field is trusted to be an `Option<_>`
+        let (inner_type, kind) = match &attrs.inner {
+            Some(inner) => (inner, FieldKind::Option),
+            None => extract_inner_type(shared_value_type(&field.ty).unwrap_or(&field.ty))?,
+        };
+
+        // With `namespace`, the methods become associated functions of a marker type and take
+        // the struct as a `pipeline` parameter; `this` is what their bodies read the fields
+        // through. Impls that stay on the struct rebind `self` to the same name.
+        let (this, rebind) = if attrs.namespace {
+            (quote! { pipeline }, quote! { let pipeline = self; })
+        } else {
+            (quote! { self }, quote! {})
+        };
+        let marker = format_ident!("{}Pipeline", struct_name, span = struct_name.span());
+
+        // A field of type `Arc<Mutex<Option<T>>>` is shared with other owners: it is read by
+        // locking and cloning the value out, and written through the lock. Each access locks
+        // separately, so steps and hooks never run while the lock is held.
+        let shared = shared_value_type(&field.ty).is_some();
+        let field_place = if shared {
+            quote! { *#this.#field_ident.lock().unwrap() }
+        } else {
+            quote! { #this.#field_ident }
+        };
+        if shared {
+            if attrs.cow {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'cow' attribute cannot borrow from a shared Arc<Mutex<..>> field",
+                ));
+            }
+            if attrs.both_receivers {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'both_receivers' attribute cannot move the value out of a shared Arc<Mutex<..>> field",
+                ));
+            }
+            if attrs.peek {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'peek' attribute cannot lend a reference past the lock of a shared Arc<Mutex<..>> field",
+                ));
+            }
+            if attrs.into_parts {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'into_parts' attribute cannot move the value out of a shared Arc<Mutex<..>> field",
+                ));
+            }
+        }
+
+        // A struct holding `Option<Self>` directly would have infinite size; recursion needs a
+        // `Box`
+        if let Type::Path(TypePath { qself: None, path }) = ungroup(inner_type) {
+            if let [segment] = path.segments.iter().collect::<Vec<_>>()[..] {
+                if segment.ident == *struct_name || segment.ident == "Self" {
+                    return Err(Error::spanned(
+                        inner_type,
+                        "Inner type must not be the struct itself",
+                    )
+                    .span_help(
+                        inner_type.span(),
+                        format!(
+                            "box recursive values instead, e.g. Option<Box<{}>>",
+                            struct_name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Inside the marker's impl, `Self` would name the marker rather than the struct
+        if attrs.namespace && mentions_self(field.ty.to_token_stream()) {
+            return Err(Error::spanned(
+                &field.ty,
+                "'namespace' attribute cannot be used with a field type naming `Self`; spell out the struct name",
+            ));
+        }
+
+        // `skip`, `skip_if`, `export_trait`, `on_none`, `accessors`, `take`, `error_enum` and
+        // `panic_on_none` are expressed in terms of `None`, which a Result field cannot produce
+        if let FieldKind::Result(_) = kind {
+            if attrs.skip {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'skip' attribute requires a field of type Option<T>",
+                ));
+            }
+            if let Some(predicate) = &attrs.skip_if {
+                return Err(Error::spanned(
+                    predicate,
+                    "'skip_if' attribute requires a field of type Option<T>",
+                ));
+            }
+            if let Some(trait_name) = &attrs.export_trait {
+                return Err(Error::spanned(
+                    trait_name,
+                    "'export_trait' attribute requires a field of type Option<T>",
+                ));
+            }
+            if let Some(hook) = &attrs.on_none {
+                return Err(Error::spanned(
+                    hook,
+                    "'on_none' attribute requires a field of type Option<T>",
+                ));
+            }
+            if attrs.accessors {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'accessors' attribute requires a field of type Option<T>",
+                ));
+            }
+            if attrs.take {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'take' attribute requires a field of type Option<T>",
+                ));
+            }
+            if attrs.force_skip_cfg {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'force_skip_cfg' attribute requires a field of type Option<T>",
+                ));
+            }
+            if attrs.ok_or {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'ok_or' attribute requires a field of type Option<T>",
+                ));
+            }
+            if let Some(error_enum) = &attrs.error_enum {
+                return Err(Error::spanned(
+                    error_enum,
+                    "'error_enum' attribute requires a field of type Option<T>",
+                ));
+            }
+            if attrs.panic_on_none.is_some() {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'panic_on_none' attribute requires a field of type Option<T>",
+                ));
+            }
+        }
+
+        // `stop_on_err_log` prints the error of a failing step, so it needs errors to print
+        let log_bounds: Vec<WherePredicate> = match kind {
+            FieldKind::Result(err_type) if attrs.stop_on_err_log => {
+                vec![parse_quote_spanned! { err_type.span()=>
+                    #err_type: ::std::fmt::Debug
+                }]
+            }
+            FieldKind::Option if attrs.stop_on_err_log => {
+                return Err(Error::spanned(
+                    &field.ty,
+                    "'stop_on_err_log' attribute requires a field of type Result<T, E>",
+                ));
+            }
+            _ => Vec::new(),
+        };
+
+        // Clone generics and add a `T: Clone` where bound to the generics for use in method
+        // definitions. The bounds carry the field type's span, so a concrete non-`Clone` type is
+        // reported there. `clone_bound` replaces them with the user's predicates, which must
+        // still imply `Clone`. With `copy` the bounds are `Copy` instead, so the seed is read
+        // from the field by copy.
+        if attrs.copy && attrs.clone_bound.is_some() {
+            return Err(Error::spanned(
+                struct_name,
+                "'clone_bound' cannot be combined with 'copy', which bounds the field's types by Copy",
+            ));
+        }
+        if attrs.copy && attrs.cow {
+            return Err(Error::spanned(
+                struct_name,
+                "'cow' attribute cannot be combined with 'copy', which already avoids cloning",
+            ));
+        }
+        let mut generics = input.generics.clone();
+        // A name rather than tokens, so each bound can give it the span of the type it bounds
+        let clone_trait = if attrs.copy { "Copy" } else { "Clone" };
+        let clone_bounds = match &attrs.clone_bound {
+            Some(predicates) => predicates.clone(),
+            None => {
+                let inner_trait = Ident::new(clone_trait, inner_type.span());
+                let mut clone_bounds: Vec<WherePredicate> =
+                    vec![parse_quote_spanned! { inner_type.span()=>
+                        #inner_type: #inner_trait
+                    }];
+                if let FieldKind::Result(err_type) = kind {
+                    let err_trait = Ident::new(clone_trait, err_type.span());
+                    clone_bounds.push(parse_quote_spanned! { err_type.span()=>
+                        #err_type: #err_trait
+                    });
+                }
+                clone_bounds
+            }
+        };
+        // `bound` adds the user's predicates to both method impls, e.g. for what their steps need
+        generics.make_where_clause().predicates.extend(
+            clone_bounds
+                .into_iter()
+                .chain(log_bounds.iter().cloned())
+                .chain(attrs.bound.iter().cloned()),
+        );
+        // `eq_check` compares each step's result with its input
+        if attrs.eq_check {
+            generics.make_where_clause().predicates.push(
+                parse_quote_spanned! { inner_type.span()=>
+                    #inner_type: PartialEq
+                },
+            );
+        }
+
+        let struct_params: Vec<TokenStream> = input
+            .generics
+            .params
+            .iter()
+            .map(|param| match param {
+                GenericParam::Lifetime(param) => param.lifetime.to_token_stream(),
+                GenericParam::Type(param) => param.ident.to_token_stream(),
+                GenericParam::Const(param) => param.ident.to_token_stream(),
+            })
+            .collect();
+
+        // Wrapper type returned by steps and methods
+        let output = kind.wrap(inner_type);
+        let skip_doc = if attrs.skip {
+            quote! {
+                ///
+                /// Always returns None because skip attribute is set.
+            }
+        } else {
+            quote! {}
+        };
+
+        // The timeout in milliseconds: the `timeout` literal, or with `timeout_field` a
+        // `timeout_ms` local that `timeout_code` reads from the field when a method is called
+        if let (Some(_), Some(timeout_field)) = (attrs.timeout, &attrs.timeout_field) {
+            return Err(Error::spanned(
+                timeout_field,
+                "'timeout_field' cannot be combined with 'timeout'",
+            ));
+        }
+        if let (true, Some(timeout_field)) = (attrs.serde, &attrs.timeout_field) {
+            return Err(Error::spanned(
+                timeout_field,
+                "'timeout_field' cannot be combined with 'serde', which builds the struct from the pipeline field alone",
+            ));
+        }
+        if let (true, Some(timeout_field)) = (attrs.from_option, &attrs.timeout_field) {
+            return Err(Error::spanned(
+                timeout_field,
+                "'timeout_field' cannot be combined with 'from_option', which builds the struct from the pipeline field alone",
+            ));
+        }
+        let timeout = match (attrs.timeout, &attrs.timeout_field) {
+            (Some(timeout), _) => Some(quote! { #timeout }),
+            (None, Some(_)) => Some(quote! { timeout_ms }),
+            (None, None) => None,
+        };
+
+        // If `timeout` attribute is set, generate code to print the timeout message on pipeline
+        // method calls
+        let timeout_code = match (&timeout, &attrs.timeout_field) {
+            (_, _) if attrs.skip => quote! {},
+            (Some(_), Some(timeout_field)) => quote! {
+                let timeout_ms = u64::from(#this.#timeout_field);
+                println!("Pipeline timeout set to {} ms", timeout_ms);
+            },
+            (Some(timeout), None) => quote! {
+                println!("Pipeline timeout set to {} ms", #timeout);
+            },
+            (None, _) => quote! {},
+        };
+
+        // The timeout is enforced: methods note when they start and check the deadline before
+        // every step after the first. `none` stops the run with the method's failure value,
+        // `panic` panics and `log` reports the overrun and keeps going. An `Option` field
+        // defaults to `none`; a `Result` field has no value to stop with, so without
+        // `timeout_action` it is not timed.
+        let timeout_action = match (attrs.timeout_action, &kind) {
+            (None, FieldKind::Option) if timeout.is_some() => Some(TimeoutAction::None),
+            (action, _) => action,
+        };
+        let deadline = match (timeout, timeout_action) {
+            (_, Some(_)) if attrs.skip => None,
+            (Some(timeout), Some(action)) => {
+                if action == TimeoutAction::None {
+                    if let FieldKind::Result(_) = kind {
+                        return Err(Error::spanned(
+                            &field.ty,
+                            "'timeout_action' `none` requires a field of type Option<T>",
+                        ));
+                    }
+                }
+                Some((timeout, action))
+            }
+            (None, Some(_)) => {
+                return Err(Error::spanned(
+                    struct_name,
+                    "'timeout_action' attribute requires 'timeout' or 'timeout_field'",
+                ));
+            }
+            _ => None,
+        };
+
+        // If `cow` is set, steps pass `Cow<T>` values borrowed from the field until a step needs
+        // to own its value, so steps returning their input unchanged never clone
+        if attrs.cow && attrs.mutate_field {
+            return Err(Error::spanned(
+                struct_name,
+                "'cow' attribute cannot be combined with 'mutate_field'",
+            ));
+        }
+        if attrs.cow && attrs.config {
+            return Err(Error::spanned(
+                struct_name,
+                "'cow' attribute cannot be combined with 'config', whose steps take owned values",
+            ));
+        }
+        let (item, item_lifetime) = if attrs.cow {
+            (
+                quote! { ::std::borrow::Cow<'__pipeline, #inner_type> },
+                quote! { '__pipeline, },
+            )
+        } else {
+            (quote! { #inner_type }, quote! {})
+        };
+        let step_output = kind.wrap(&item);
+        let inner_item = quote! { #inner_type };
+
+        // If `mutate_field` is set, methods store every successful step's value
+        let success = kind.success();
+        let after_step = if attrs.mutate_field {
+            // Spelled out so an inner type like `&'a T` stores the reference rather than a `T`
+            quote! { #field_place = #success(Clone::clone(&value)); }
+        } else {
+            quote! {}
+        };
+
+        let on_none_trait =
+            format_ident!("{}OnNone", struct_name.unraw(), span = struct_name.span());
+
+        // If `trace_to` is set, hand every successful step's value to the sink by reference
+        let trace_owned = match &attrs.trace_to {
+            Some(sink) => quote! { #sink(&value); },
+            None => quote! {},
+        };
+        let trace_step = match &attrs.trace_to {
+            Some(sink) if attrs.cow => quote! { #sink(&*value); },
+            _ => trace_owned.clone(),
+        };
+
+        // `processN` methods take `N - 1` steps, for every `N` from 3 up to `max_arity`. With
+        // `min_steps`, methods taking fewer steps than required are not generated at all.
+        let max_arity = attrs.max_arity.unwrap_or(4);
+        let min_steps = attrs.min_steps.unwrap_or(0);
+        if min_steps >= max_arity {
+            return Err(Error::spanned(
+                struct_name,
+                format!(
+                    "'min_steps' must be less than 'max_arity' ({}), otherwise no methods are generated",
+                    max_arity
+                ),
+            ));
+        }
+        if let Some(names) = &attrs.step_names {
+            if attrs.version.unwrap_or(1) != 1 {
+                return Err(Error::spanned(
+                    struct_name,
+                    "'step_names' attribute requires version 1, whose methods take a fixed number of steps",
+                ));
+            }
+            if names.len() != max_arity - 1 {
+                return Err(Error::spanned(
+                    struct_name,
+                    format!(
+                        "'step_names' lists {} names, but the longest generated method takes {} steps",
+                        names.len(),
+                        max_arity - 1
+                    ),
+                ));
+            }
+        }
+        let arities: Vec<usize> = (3..=max_arity).filter(|&arity| arity > min_steps).collect();
+
+        // `version = 1` (the default) names the core methods after their arity, `process3` and
+        // `process4`; `version = 2` replaces them with a single `process` taking any number of
+        // steps
+        let fixed_arity = attrs.version.unwrap_or(1) == 1;
+
+        // If `doc_hidden` is set, the inherent impls carrying the generated methods are hidden
+        // from rustdoc, so they stay out of the deriving crate's public docs
+        let hidden = if attrs.doc_hidden {
+            quote! { #[doc(hidden)] }
+        } else {
+            quote! {}
+        };
+
+        // If `inline_always` is set, the core methods ask to be inlined, so the step closures can
+        // be inlined into the caller along with them
+        let inline = if attrs.inline_always {
+            quote! { #[inline(always)] }
+        } else {
+            quote! {}
+        };
+
+        // If `panic_on_none` is set, the core methods return the inner value itself, panicking
+        // with the configured message where they would have returned `None`
+        let (core_output, core_panics) = match &attrs.panic_on_none {
+            Some(message) => {
+                let doc = format!(
+                    " Panics with {:?} if the field is `None` or a step returns `None`.",
+                    message
+                );
+                (
+                    item.clone(),
+                    quote! {
+                        ///
+                        /// # Panics
+                        #[doc = #doc]
+                    },
+                )
+            }
+            None => (step_output.clone(), quote! {}),
+        };
+
+        // `par_iter` runs the steps over the elements of a `Vec`
+        let par_iter_element = if attrs.par_iter {
+            match (&kind, vec_element_type(inner_type)) {
+                (FieldKind::Option, Some(element)) => Some(element),
+                _ => {
+                    return Err(Error::spanned(
+                        &field.ty,
+                        "'par_iter' attribute requires a field of type Option<Vec<T>>",
+                    ));
+                }
+            }
+        } else {
+            None
+        };
+
+        // `derive_debug` prints the pipeline field alone
+        if let (true, Some(timeout_field)) = (attrs.derive_debug, &attrs.timeout_field) {
+            return Err(Error::spanned(
+                timeout_field,
+                "'derive_debug' attribute requires a struct with a single field",
+            ));
+        }
+
+        Ok(Context {
+            attrs,
+            input,
+            struct_name,
+            field_ident,
+            inner_type,
+            kind,
+            shared,
+            this,
+            rebind,
+            self_receiver: quote! { &self },
+            marker,
+            field_place,
+            generics,
+            struct_params,
+            output,
+            skip_doc,
+            timeout_code,
+            deadline,
+            item,
+            item_lifetime,
+            step_output,
+            inner_item,
+            success,
+            after_step,
+            on_none_trait,
+            log_bounds,
+            trace_step,
+            trace_owned,
+            arities,
+            par_iter_element,
+            fixed_arity,
+            min_steps,
+            hidden,
+            inline,
+            core_output,
+            core_panics,
+        })
+    }
+
+    /// Type the generated methods are declared on: the struct, or its marker with `namespace`.
+    pub fn target(&self) -> &Ident {
+        if self.attrs.namespace {
+            &self.marker
+        } else {
+            self.struct_name
+        }
+    }
+
+    /// Receiver taking the struct by `reference`, e.g. `&self`, or with `namespace` a `pipeline`
+    /// parameter of the struct type.
+    pub fn struct_receiver(&self, reference: TokenStream) -> TokenStream {
+        let struct_name = self.struct_name;
+        let (_, struct_ty_generics, _) = self.input.generics.split_for_impl();
+        if self.attrs.namespace {
+            quote! { pipeline: #reference #struct_name #struct_ty_generics }
+        } else {
+            quote! { #reference self }
+        }
+    }
+
+    pub fn ref_receiver(&self) -> TokenStream {
+        self.struct_receiver(quote! { & })
+    }
+
+    pub fn mut_receiver(&self) -> TokenStream {
+        self.struct_receiver(quote! { &mut })
+    }
+
+    /// Receiver of the methods running the steps on `item` values. With `mutate_field`, they
+    /// borrow mutably to store every step's value; a shared field is written through its lock,
+    /// which only needs a shared borrow. With `cow`, the borrow is the `'__pipeline` the values
+    /// borrow.
+    pub fn receiver(&self) -> TokenStream {
+        if self.attrs.mutate_field && !self.shared {
+            self.mut_receiver()
+        } else if self.attrs.cow {
+            self.struct_receiver(quote! { &'__pipeline })
+        } else {
+            self.ref_receiver()
+        }
+    }
+
+    /// Guards `seed`, a wrapper of `ty`, so that a run skipped at run time starts from `None`.
+    ///
+    /// With `skip_if`, every seed is guarded by the predicate, so a run it skips behaves as if
+    /// the field were `None`, whatever the method's shape. With `force_skip_cfg`, building the
+    /// deriving crate with `--cfg pipeline_force_skip` skips the pipeline the same way; as the
+    /// `cfg!` is expanded there, it only affects crates that opt in.
+    pub fn skippable(&self, seed: TokenStream, ty: &dyn ToTokens) -> TokenStream {
+        // The cfg is the deriving crate's own, which rustc would report as unexpected unless
+        // that crate declares it
+        let force_skip = quote! {
+            {
+                #[allow(unexpected_cfgs)]
+                let force_skip = cfg!(pipeline_force_skip);
+                force_skip
+            }
+        };
+        match (&self.attrs.skip_if, self.attrs.force_skip_cfg) {
+            _ if self.attrs.skip => seed,
+            (Some(predicate), true) => quote! {
+                if #force_skip || #predicate() { None::<#ty> } else { #seed }
+            },
+            (Some(predicate), false) => quote! {
+                if #predicate() { None::<#ty> } else { #seed }
+            },
+            (None, true) => quote! {
+                if #force_skip { None::<#ty> } else { #seed }
+            },
+            (None, false) => seed,
+        }
+    }
+
+    /// The value each chain starts from. With `skip` the seed is `None`, so every chain
+    /// short-circuits without running its steps.
+    pub fn seed(&self) -> TokenStream {
+        let Context {
+            field_ident,
+            this,
+            inner_type,
+            ..
+        } = self;
+        self.skippable(
+            match self.kind {
+                _ if self.attrs.skip => quote! { None::<#inner_type> },
+                _ if self.attrs.copy && self.shared => {
+                    quote! { { let seed = *#this.#field_ident.lock().unwrap(); seed } }
+                }
+                _ if self.attrs.copy => quote! { #this.#field_ident },
+                FieldKind::Option => self.read_field(quote! { .as_ref().cloned() }),
+                FieldKind::Result(_) => self.read_field(quote! { .clone() }),
+            },
+            inner_type,
+        )
+    }
+
+    /// Reads the field followed by `access`, e.g. `.clone()`, locking a shared field only for the
+    /// duration of the read.
+    pub fn read_field(&self, access: TokenStream) -> TokenStream {
+        let Context {
+            field_ident, this, ..
+        } = self;
+        if self.shared {
+            quote! { { let seed = #this.#field_ident.lock().unwrap()#access; seed } }
+        } else {
+            quote! { #this.#field_ident #access }
+        }
+    }
+
+    /// The value each chain over `item` values starts from, borrowing the field with `cow`.
+    pub fn chain_seed(&self) -> TokenStream {
+        if !self.attrs.cow {
+            return self.seed();
+        }
+        let Context {
+            field_ident,
+            item,
+            this,
+            ..
+        } = self;
+        self.skippable(
+            match self.kind {
+                _ if self.attrs.skip => quote! { None::<#item> },
+                FieldKind::Option => {
+                    quote! { #this.#field_ident.as_ref().map(::std::borrow::Cow::Borrowed) }
+                }
+                FieldKind::Result(_) => quote! {
+                    #this.#field_ident
+                        .as_ref()
+                        .map(::std::borrow::Cow::Borrowed)
+                        .map_err(Clone::clone)
+                },
+            },
+            item,
+        )
+    }
+
+    /// Whether methods taking `steps` steps are generated under `min_steps`.
+    pub fn steps_allowed(&self, steps: usize) -> bool {
+        steps >= self.min_steps
+    }
+
+    /// If `on_none` is set, calls the hook with the index of the step that returned `None` (`0`
+    /// for a missing seed) right before a run short-circuits, through the trait declared by
+    /// `methods::items::on_none_items`.
+    pub fn on_none(&self, index: &dyn ToTokens) -> TokenStream {
+        let on_none_trait = &self.on_none_trait;
+        match &self.attrs.on_none {
+            Some(hook) if !self.attrs.skip => quote_spanned! { hook.span()=>
+                #on_none_trait::call(&#hook, #index);
+            },
+            _ => quote! {},
+        }
+    }
+
+    /// What timeout and log messages report for the step run at `index` (1-based, 0 for the
+    /// seed) whose parameter is `f{number}`: the index, or with `step_names` the parameter's name.
+    pub fn step_label(&self, index: usize, number: usize) -> TokenStream {
+        match &self.attrs.step_names {
+            Some(names) => {
+                let name = number
+                    .checked_sub(1)
+                    .map_or("seed", |number| &names[number]);
+                quote! { #name }
+            }
+            None => quote! { #index },
+        }
+    }
+
+    /// Notes when the method started, if `timeout_action` enforces the timeout.
+    pub fn deadline_start(&self) -> TokenStream {
+        if self.deadline.is_some() {
+            quote! { let started = ::std::time::Instant::now(); }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Checks the deadline before the step at `index`, if `timeout_action` is set. `none` runs
+    /// `expire` to stop the run with the method's failure value, `panic` panics and `log`
+    /// reports the overrun and keeps going.
+    pub fn deadline_check(&self, index: &dyn ToTokens, expire: TokenStream) -> TokenStream {
+        let Some((timeout, action)) = &self.deadline else {
+            return quote! {};
+        };
+        let on_expire = match action {
+            TimeoutAction::None => expire,
+            TimeoutAction::Panic => quote! {
+                panic!("Pipeline timeout of {} ms exceeded before step {}", #timeout, #index);
+            },
+            TimeoutAction::Log => quote! {
+                eprintln!("Pipeline timeout of {} ms exceeded before step {}", #timeout, #index);
+            },
+        };
+        quote! {
+            if started.elapsed() > ::std::time::Duration::from_millis(#timeout) {
+                #on_expire
+            }
+        }
+    }
+
+    /// If `bench` is set, passes every step's value through `black_box`, so benchmarks measure
+    /// the steps even when the optimizer could see through them.
+    pub fn observe(&self, value: TokenStream) -> TokenStream {
+        if self.attrs.bench {
+            quote! { ::std::hint::black_box(#value) }
+        } else {
+            value
+        }
+    }
+
+    /// Calls `step` on `value`. With `stop_on_err_log`, an error is printed with the step's label
+    /// before it is propagated.
+    pub fn call_step(&self, step: TokenStream, label: TokenStream) -> TokenStream {
+        if self.log_bounds.is_empty() {
+            quote! { #step(value) }
+        } else {
+            quote! {
+                #step(value).map_err(|err| {
+                    eprintln!("Pipeline step {} failed: {:?}", #label, err);
+                    err
+                })
+            }
+        }
+    }
+
+    /// Builds a method body threading `seed` through `steps` in order, running `after_step` after
+    /// each successful step. Each step gets its own type-annotated binding and early return, so a
+    /// mismatching closure is reported at that step rather than the whole chain, and a failing
+    /// run stops right where it failed.
+    ///
+    /// With `eq_check`, a chain stops with the current value as soon as a step returns a value
+    /// equal to its input, so the remaining steps are skipped. Only the core methods pass
+    /// `eq_check`.
+    pub fn chain_steps(
+        &self,
+        seed: &TokenStream,
+        item: &TokenStream,
+        after_step: &TokenStream,
+        steps: &[Ident],
+        eq_check: bool,
+    ) -> TokenStream {
+        let Context { kind, success, .. } = self;
+        let start = kind.unwrap_or_return(seed, self.on_none(&0usize));
+        let bindings = steps.iter().enumerate().map(|(index, step)| {
+            let index = index + 1;
+            let label = self.step_label(index, step_number(step));
+            let (keep_input, compare) = if eq_check && index < steps.len() {
+                (
+                    quote! { let input: #item = Clone::clone(&value); },
+                    quote! {
+                        if value == input {
+                            return #success(value);
+                        }
+                    },
+                )
+            } else {
+                (quote! {}, quote! {})
+            };
+            let value = self.observe(kind.unwrap_or_return(
+                self.call_step(quote! { #step }, label.clone()),
+                self.on_none(&index),
+            ));
+            let check = if index > 1 {
+                let on_none = self.on_none(&index);
+                self.deadline_check(
+                    &label,
+                    quote! {
+                        #on_none
+                        return None;
+                    },
+                )
+            } else {
+                quote! {}
+            };
+            quote! {
+                #check
+                #keep_input
+                let value: #item = #value;
+                #after_step
+                #compare
+            }
+        });
+        let deadline_start = self.deadline_start();
+        quote! {
+            #deadline_start
+            let value: #item = #start;
+            #(#bindings)*
+            #success(value)
+        }
+    }
+
+    /// `chain_steps` without `eq_check`.
+    pub fn chain_from(
+        &self,
+        seed: &TokenStream,
+        item: &TokenStream,
+        after_step: &TokenStream,
+        steps: &[Ident],
+    ) -> TokenStream {
+        self.chain_steps(seed, item, after_step, steps, false)
+    }
+
+    /// What runs after each successful step of a chain over `item` values: `after_step`, then
+    /// `trace_step`.
+    pub fn step_hooks(&self) -> TokenStream {
+        let Context {
+            after_step,
+            trace_step,
+            ..
+        } = self;
+        quote! {
+            #after_step
+            #trace_step
+        }
+    }
+
+    /// Body of a core method running `body`, panicking with the `panic_on_none` message where
+    /// it would have returned `None`.
+    pub fn core_body(&self, body: TokenStream) -> TokenStream {
+        let step_output = &self.step_output;
+        match &self.attrs.panic_on_none {
+            Some(message) => quote! {
+                let run = || -> #step_output { #body };
+                run().expect(#message)
+            },
+            None => body,
+        }
+    }
+
+    /// The struct's generics without their defaults, which are only allowed on the struct
+    /// itself, for the helper types declared next to it.
+    pub fn helper_generics(&self) -> Generics {
+        let mut helper_generics = self.input.generics.clone();
+        for param in &mut helper_generics.params {
+            match param {
+                GenericParam::Type(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                GenericParam::Const(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                GenericParam::Lifetime(_) => {}
+            }
+        }
+        helper_generics
+    }
+
+    /// Helper type parameter of a generated method, such as the collection `C` or the error `E`,
+    /// renamed to `__C`, `__E`, ... if the struct already declares a parameter of that name or is
+    /// itself named so, as `namespace` signatures spell out the struct type.
+    pub fn method_param(&self, name: &str) -> Ident {
+        let taken = *self.struct_name == name
+            || self
+                .struct_params
+                .iter()
+                .any(|param| param.to_string() == name);
+        let name = if taken {
+            format!("__{}", name)
+        } else {
+            name.to_string()
+        };
+        Ident::new(&name, self.struct_name.span())
+    }
+
+    /// Chains `steps` over `item` values from the field, running the step hooks after each.
+    pub fn chain(&self, steps: &[Ident]) -> TokenStream {
+        self.chain_from(&self.chain_seed(), &self.item, &self.step_hooks(), steps)
+    }
+}
@@ -1,47 +1,76 @@
+use proc_macro_error2::Diagnostic;
 use proc_macro2::{Span, TokenStream};
 use quote::ToTokens;
 use syn::Error as SynError;
 
 /// Wrapper type for syn::Error providing convenient constructors and conversions.
+///
+/// Help notes can be attached to point at related locations; they are rendered through
+/// `proc-macro-error2` so they get their own spans on nightly and inline text on stable.
 #[derive(Debug)]
-pub struct Error(SynError);
+pub struct Error {
+    inner: SynError,
+    helps: Vec<(Span, String)>,
+}
 
 impl Error {
     /// Create a new error with a message and a span pointing to the error location.
     pub fn new<T: ToString>(msg: T, span: Span) -> Self {
-        Self(SynError::new(span, msg.to_string()))
+        Self::from(SynError::new(span, msg.to_string()))
     }
 
     /// Create a new error with a message and tokens to which the error will be spanned.
     pub fn spanned<T: ToString>(tokens: impl ToTokens, msg: T) -> Self {
-        Self(SynError::new_spanned(tokens, msg.to_string()))
+        Self::from(SynError::new_spanned(tokens, msg.to_string()))
+    }
+
+    /// Attach a help note pointing at `span`, shown alongside the main message.
+    pub fn span_help<T: ToString>(mut self, span: Span, msg: T) -> Self {
+        self.helps.push((span, msg.to_string()));
+        self
     }
 
     /// Get a reference to the inner syn::Error.
     pub fn as_syn(&self) -> &SynError {
-        &self.0
+        &self.inner
+    }
+
+    /// Convert the error into a `proc-macro-error2` diagnostic carrying any attached help notes.
+    pub fn into_diagnostic(self) -> Diagnostic {
+        self.helps
+            .into_iter()
+            .fold(Diagnostic::from(self.inner), |diag, (span, msg)| {
+                diag.span_help(span, msg)
+            })
     }
 
     /// Convert the error into a compile error token stream by reference.
     pub fn to_compile_error(&self) -> TokenStream {
-        self.0.to_compile_error()
+        Self {
+            inner: self.inner.clone(),
+            helps: self.helps.clone(),
+        }
+        .into_compile_error()
     }
 
     /// Convert the error into a compile error token stream, consuming self.
     pub fn into_compile_error(self) -> TokenStream {
-        self.0.to_compile_error()
+        self.into_diagnostic().into_token_stream()
     }
 }
 
 impl From<Error> for SynError {
     fn from(err: Error) -> Self {
-        err.0
+        err.inner
     }
 }
 
 impl From<SynError> for Error {
     fn from(err: SynError) -> Self {
-        Self(err)
+        Self {
+            inner: err,
+            helps: Vec::new(),
+        }
     }
 }
 
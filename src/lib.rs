@@ -1,11 +1,14 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
+use proc_macro_error2::{proc_macro_error, set_dummy};
 use syn::parse_macro_input;
 
 mod attributes;
 mod codegen;
+mod context;
 mod errors;
+mod methods;
 mod pipeline;
 
 /// Derive macro implementing `Pipeline` for structs with `Option<T>` fields.
@@ -14,21 +17,44 @@ mod pipeline;
 /// then generates pipeline methods that enable monadic chaining of closures
 /// over the optional value.
 ///
-/// Returns compiler errors if parsing or generation fail.
+/// Returns compiler errors if parsing or generation fail, emitted through `proc-macro-error2`
+/// so they can carry help notes.
+#[proc_macro_error]
 #[proc_macro_derive(Pipeline, attributes(pipeline))]
 pub fn pipeline_derive_macro(input: TokenStream) -> TokenStream {
     // Parse the input tokens into a DeriveInput syntax tree
     let input = parse_macro_input!(input as syn::DeriveInput);
 
-    // Parse custom pipeline attributes, defaulting on error by emitting compile error
-    let attrs = match attributes::parse_attributes(&input) {
-        Ok(a) => a,
-        Err(err) => return err.to_compile_error().into(),
-    };
-
-    // Generate pipeline implementation, or emit compile error if generation fails
-    match pipeline::pipeline_derive(input, &attrs) {
-        Ok(tokens) => tokens.into(),
-        Err(err) => err.to_compile_error().into(),
+    // Parse attributes and generate the pipeline implementation, emitting diagnostics on failure
+    let marker_impl = codegen::qualified_marker_impl(&input);
+    match codegen::pipeline_derive(input) {
+        Ok(mut tokens) => {
+            tokens.extend(marker_impl);
+            tokens.into()
+        }
+        Err(err) => {
+            // The tokens returned next to an emitted error are discarded in favour of the dummy
+            set_dummy(marker_impl);
+            err.into_diagnostic().emit();
+            TokenStream::new()
+        }
     }
 }
+
+/// Qualified form of the derive's `#[pipeline(...)]` attribute.
+///
+/// Written as `#[pipeline_derive::pipeline(...)]` below `#[derive(Pipeline)]`, it configures the
+/// derive exactly like `#[pipeline(...)]`, for crates where another `pipeline` attribute is in
+/// scope. Under a renamed or re-exported dependency, declare the path it is written with, e.g.
+/// `#[pd::pipeline(crate = "pd", ...)]`. The derive reads it before it expands, and the attribute itself leaves the struct
+/// unchanged. Without a `#[derive(Pipeline)]` above it to read the options, it is an error.
+#[proc_macro_attribute]
+pub fn pipeline(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut output = item.clone();
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    output.extend(TokenStream::from(codegen::qualified_attribute(
+        args.into(),
+        &input,
+    )));
+    output
+}
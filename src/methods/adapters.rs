@@ -0,0 +1,573 @@
+use crate::context::Context;
+use crate::pipeline::{FieldKind, step_params};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::Ident;
+
+/// `process3_rev` runs its two steps in reverse order.
+pub fn process3_rev(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let reversed: Vec<Ident> = steps.iter().rev().cloned().collect();
+    let process3_rev_body = cx.chain(&reversed);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two closure steps applied in reverse order,
+        /// running `f2` then `f1`.
+        ///
+        /// Lets callers pass steps in their logical order and run them backwards, e.g.
+        /// to undo a pipeline whose steps are passed as their inverses.
+        #skip_doc
+        pub fn process3_rev<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> #step_output
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #process3_rev_body
+        }
+    }
+}
+
+/// `process3_map` finishes a successful run with an infallible transform, e.g. into an output
+/// type, keeping the fallible steps and the final conversion apart.
+pub fn process3_map(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        kind,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let finally = Ident::new("finally", struct_name.span());
+    let (map, mapped) = (cx.method_param("M"), cx.method_param("U"));
+    let body = cx.chain(&steps);
+    let output = kind.wrap(&mapped);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, then maps a successful
+        /// result with `finally`.
+        #skip_doc
+        pub fn process3_map<#item_lifetime #(#step_types,)* #map, #mapped>(#receiver, #(#steps: #step_types,)* #finally: #map) -> #output
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+            #map: FnOnce(#item) -> #mapped,
+        {
+            #timeout_code
+            let run = || -> #step_output { #body };
+            run().map(#finally)
+        }
+    }
+}
+
+/// `process3_chain` stores the result of the run back into the field and hands the struct back,
+/// so runs can be chained fluently. A shared field is written through its lock, which needs no
+/// `mut` binding.
+pub fn process3_chain(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        field_place,
+        this,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let (_, struct_ty_generics, _) = cx.input.generics.split_for_impl();
+    let chain_receiver = match (cx.attrs.namespace, cx.shared) {
+        (true, true) => quote! { pipeline: #struct_name #struct_ty_generics },
+        (true, false) => quote! { mut pipeline: #struct_name #struct_ty_generics },
+        (false, true) => quote! { self },
+        (false, false) => quote! { mut self },
+    };
+    let body = cx.chain_from(&cx.seed(), inner_item, trace_owned, &steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, stores the result in the
+        /// field and returns the struct, e.g. for `p.process3_chain(f1, f2).process3_chain(f3, f4)`.
+        #skip_doc
+        pub fn process3_chain<#(#step_types),*>(#chain_receiver, #(#steps: #step_types),*) -> #struct_name #struct_ty_generics
+        where
+            #(#step_types: FnOnce(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            let run = || -> #output { #body };
+            let result = run();
+            #field_place = result;
+            #this
+        }
+    }
+}
+
+/// `process3_map_err` adapts the error of a `Result` pipeline to the caller's error type at the
+/// end of the run, so each step can keep returning the field's own error type.
+pub fn process3_map_err(cx: &Context) -> TokenStream {
+    let FieldKind::Result(err_type) = cx.kind else {
+        return quote! {};
+    };
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let map_err = Ident::new("map_err", struct_name.span());
+    let (map, mapped) = (cx.method_param("M"), cx.method_param("E2"));
+    let body = cx.chain(&steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, then maps an error
+        /// with `map_err`.
+        #skip_doc
+        pub fn process3_map_err<#item_lifetime #(#step_types,)* #map, #mapped>(#receiver, #(#steps: #step_types,)* #map_err: #map) -> ::core::result::Result<#item, #mapped>
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+            #map: FnOnce(#err_type) -> #mapped,
+        {
+            #timeout_code
+            let run = || -> #step_output { #body };
+            run().map_err(#map_err)
+        }
+    }
+}
+
+/// If `ok_or` is set, `process3_ok_or` turns a `None` from an `Option` pipeline into a
+/// caller-chosen error, so the run can be propagated with `?` from a function returning `Result`.
+pub fn process3_ok_or(cx: &Context) -> TokenStream {
+    if !cx.attrs.ok_or || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let err = Ident::new("err", struct_name.span());
+    let err_type = cx.method_param("E");
+    let body = cx.chain(&steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning `err`
+        /// instead of `None` if the run short-circuits.
+        #skip_doc
+        pub fn process3_ok_or<#item_lifetime #(#step_types,)* #err_type>(#receiver, #err: #err_type, #(#steps: #step_types),*) -> ::core::result::Result<#item, #err_type>
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            let run = || -> #step_output { #body };
+            run().ok_or(#err)
+        }
+    }
+}
+
+/// If `with_step` is set, bind the first of two steps to a clone of the stored value, returning
+/// a closure that awaits the second; it is passed as `&dyn Fn`, as a closure cannot be generic.
+pub fn with_step(cx: &Context) -> TokenStream {
+    if !cx.attrs.with_step || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        struct_params,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let (ref_receiver, seed) = (cx.ref_receiver(), cx.seed());
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let (bound, rest) = (&steps[0], &steps[1]);
+    let bound_type = &step_types[0];
+    let stored = Ident::new("stored", struct_name.span());
+    let body = cx.chain_from(
+        &quote! { Clone::clone(&#stored) },
+        inner_item,
+        trace_owned,
+        &steps,
+    );
+    quote_spanned! { struct_name.span()=>
+        /// Binds `f1` as the first of two chained closure steps, returning a closure that
+        /// runs it and then the second step it is called with on the current value, e.g.
+        /// `let partial = p.with_step(f1); partial(&f2)`.
+        ///
+        /// The value is cloned when `with_step` is called, so the closure does not borrow the
+        /// struct and later changes to the field are not seen.
+        #skip_doc
+        pub fn with_step<#bound_type>(#ref_receiver, #bound: #bound_type) -> impl Fn(&dyn Fn(#inner_type) -> #output) -> #output + use<#(#struct_params,)* #bound_type>
+        where
+            #bound_type: Fn(#inner_type) -> #output,
+        {
+            #timeout_code
+            let #stored: #output = #seed;
+            move |#rest: &dyn Fn(#inner_type) -> #output| -> #output { #body }
+        }
+    }
+}
+
+/// `process3_total_time` measures the whole run with one `Instant`, for end-to-end latency.
+pub fn process3_total_time(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let chained = cx.chain(&steps);
+    let body = quote! {
+        let run = || -> #step_output { #chained };
+        let measured = ::std::time::Instant::now();
+        let result = run();
+        (result, measured.elapsed())
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning the result
+        /// together with the time the whole run took.
+        #skip_doc
+        pub fn process3_total_time<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> (#step_output, ::std::time::Duration)
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_all_errors` treats the steps of a `Result` pipeline as independent validations of
+/// the seed: every step runs on a clone of it, and all errors are gathered instead of the first.
+pub fn process3_all_errors(cx: &Context) -> TokenStream {
+    let FieldKind::Result(err_type) = cx.kind else {
+        return quote! {};
+    };
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let (receiver, chain_seed) = (cx.receiver(), cx.chain_seed());
+    let deadline_start = cx.deadline_start();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let validations = steps.iter().enumerate().map(|(index, step)| {
+        let index = index + 1;
+        let label = cx.step_label(index, index);
+        let check = if index > 1 {
+            cx.deadline_check(&label, quote! {})
+        } else {
+            quote! {}
+        };
+        let result = cx.observe(cx.call_step(quote! { #step }, label));
+        quote! {
+            #check
+            let value: #item = ::core::clone::Clone::clone(&seed);
+            if let Err(err) = #result {
+                errors.push(err);
+            }
+        }
+    });
+    let body = quote! {
+        #deadline_start
+        let seed: #item = match #chain_seed {
+            Ok(value) => value,
+            Err(err) => return Err(::std::vec![err]),
+        };
+        let mut errors: ::std::vec::Vec<#err_type> = ::std::vec::Vec::new();
+        #(#validations)*
+        if errors.is_empty() { Ok(seed) } else { Err(errors) }
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Runs two closure steps as independent validations, each on a clone of the inner
+        /// value, returning the value unchanged if all pass and every error otherwise.
+        ///
+        /// The values returned by passing steps are discarded. An `Err` field yields a
+        /// single error without running any step.
+        #skip_doc
+        pub fn process3_all_errors<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> ::core::result::Result<#item, ::std::vec::Vec<#err_type>>
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_into` lets steps return a bare value, an `Option`, or anything else converting into
+/// one; each step is rebound to a closure converting its result, then chained as usual.
+pub fn process3_into(cx: &Context) -> TokenStream {
+    if !matches!(cx.kind, FieldKind::Option) || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let returns: Vec<Ident> = (1..=2)
+        .map(|n| cx.method_param(&format!("R{}", n)))
+        .collect();
+    let body = cx.chain(&steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, which may return the
+        /// next value directly instead of wrapping it in `Some`.
+        #skip_doc
+        pub fn process3_into<#item_lifetime #(#step_types,)* #(#returns),*>(#receiver, #(#steps: #step_types),*) -> #step_output
+        where
+            #(#step_types: FnOnce(#item) -> #returns,)*
+            #(#returns: Into<#step_output>,)*
+        {
+            #(let #steps = move |value: #item| -> #step_output { #steps(value).into() };)*
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// If `process_mixed` is set, pair a fallible first step with an infallible second one, which is
+/// wrapped in the success variant, so total steps need no `Some`/`Ok`.
+pub fn process_mixed(cx: &Context) -> TokenStream {
+    if !cx.attrs.process_mixed || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        success,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let total = &steps[1];
+    let (fallible_type, total_type) = (&step_types[0], &step_types[1]);
+    let body = cx.chain(&steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with a fallible step `f1`, then the infallible step `f2`.
+        #skip_doc
+        pub fn process_mixed<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> #step_output
+        where
+            #fallible_type: FnOnce(#item) -> #step_output,
+            #total_type: FnOnce(#item) -> #item,
+        {
+            let #total = move |value: #item| -> #step_output { #success(#total(value)) };
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_within` takes its time budget at call time. Each step is wrapped so that finishing
+/// past the budget counts as the step returning `None`, reported to the hooks like one.
+pub fn process3_within(cx: &Context) -> TokenStream {
+    if !matches!(cx.kind, FieldKind::Option) || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let budget = Ident::new("budget", struct_name.span());
+    let body = cx.chain(&steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning `None` if
+        /// a step finishes after `budget` has elapsed since the call.
+        #skip_doc
+        pub fn process3_within<#item_lifetime #(#step_types),*>(#receiver, #budget: ::std::time::Duration, #(#steps: #step_types),*) -> #step_output
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            let started = ::std::time::Instant::now();
+            #(let #steps = move |value: #item| -> #step_output {
+                #steps(value).filter(|_| started.elapsed() <= #budget)
+            };)*
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_ctx` hands a borrowed context to every step, so shared read-only state such as a
+/// configuration or a database handle reaches the steps without each closure capturing it.
+pub fn process3_ctx(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let ctx = Ident::new("ctx", struct_name.span());
+    let context = cx.method_param("C");
+    let body = cx.chain(&steps);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, each called with `ctx`
+        /// and the current value.
+        #skip_doc
+        pub fn process3_ctx<#item_lifetime #context: ?Sized, #(#step_types),*>(#receiver, #ctx: &#context, #(#steps: #step_types),*) -> #step_output
+        where
+            #(#step_types: FnOnce(&#context, #item) -> #step_output,)*
+        {
+            #(let #steps = move |value: #item| -> #step_output { #steps(#ctx, value) };)*
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// If `error_enum` is set, `try_process3` reports why a run stopped: a step returning `None` (or a
+/// missing seed) becomes `EarlyStop` and an expired `timeout_action = "none"` deadline becomes
+/// `Timeout`, each carrying the index of the step concerned.
+pub fn try_process3(cx: &Context) -> TokenStream {
+    let Some(error_enum) = &cx.attrs.error_enum else {
+        return quote! {};
+    };
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let stop = |index: usize, variant: TokenStream| {
+        let on_none = cx.on_none(&index);
+        quote! {
+            #on_none
+            return Err(#error_enum::#variant { step: #index });
+        }
+    };
+    let start = stop(0, quote! { EarlyStop });
+    let step_hooks = cx.step_hooks();
+    let bindings = steps.iter().enumerate().map(|(index, step)| {
+        let index = index + 1;
+        let check = if index > 1 {
+            cx.deadline_check(
+                &cx.step_label(index, index),
+                stop(index, quote! { Timeout }),
+            )
+        } else {
+            quote! {}
+        };
+        let fail = stop(index, quote! { EarlyStop });
+        let value = cx.observe(quote! {
+            match #step(value) {
+                Some(value) => value,
+                None => {
+                    #fail
+                }
+            }
+        });
+        quote! {
+            #check
+            let value: #item = #value;
+            #step_hooks
+        }
+    });
+    let chain_seed = cx.chain_seed();
+    let deadline_start = cx.deadline_start();
+    let body = quote! {
+        #deadline_start
+        let value: #item = match #chain_seed {
+            Some(value) => value,
+            None => {
+                #start
+            }
+        };
+        #(#bindings)*
+        Ok(value)
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning an error
+        /// naming the step at which the run stopped instead of `None`.
+        #skip_doc
+        pub fn try_process3<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> ::core::result::Result<#item, #error_enum>
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::context::Context;
+use crate::pipeline::{count_word, step_params};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+
+/// `processN` for every arity, taking `N - 1` steps chained in order. They are the core methods
+/// of version 1, so `eq_check`, `panic_on_none` and `inline_always` apply to them.
+pub fn fixed_arity_methods(cx: &Context) -> TokenStream {
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        inline,
+        core_output,
+        core_panics,
+        ..
+    } = cx;
+    let (receiver, chain_seed) = (cx.receiver(), cx.chain_seed());
+    let step_hooks = cx.step_hooks();
+    let methods = cx.arities.iter().filter(|_| cx.fixed_arity).map(|&arity| {
+        let name = format_ident!("process{}", arity);
+        let (steps, step_types) = step_params(arity - 1, struct_name.span());
+        let doc = format!(
+            " Processes the inner value with {} chained closure steps.",
+            count_word(arity - 1)
+        );
+        let body = cx.core_body(cx.chain_steps(
+            &chain_seed,
+            item,
+            &step_hooks,
+            &steps,
+            cx.attrs.eq_check,
+        ));
+        quote_spanned! { struct_name.span()=>
+            #[doc = #doc]
+            #skip_doc
+            #core_panics
+            #inline
+            pub fn #name<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> #core_output
+            where
+                #(#step_types: FnOnce(#item) -> #step_output,)*
+            {
+                #timeout_code
+                #body
+            }
+        }
+    });
+    quote! { #(#methods)* }
+}
+
+/// If `first_some` is set, `processN_first_some` treats the steps as alternatives: each gets its
+/// own copy of the seed and the first success is returned, without trying the rest.
+pub fn first_some_methods(cx: &Context) -> TokenStream {
+    let Context {
+        struct_name,
+        kind,
+        item,
+        item_lifetime,
+        step_output,
+        success,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let step_hooks = cx.step_hooks();
+    let deadline_start = cx.deadline_start();
+    let methods = cx.arities.iter().filter(|_| cx.attrs.first_some).map(|&arity| {
+        let name = format_ident!("process{}_first_some", arity);
+        let (steps, step_types) = step_params(arity - 1, struct_name.span());
+        let doc = format!(
+            " Tries {} closure steps on the inner value in order, returning the first success.",
+            count_word(arity - 1)
+        );
+        let start = kind.unwrap_or_return(cx.chain_seed(), cx.on_none(&0usize));
+        // Deadline check before the alternative at `index`, skipping the remaining ones
+        let check = |index: usize| {
+            if index > 1 {
+                let on_none = cx.on_none(&index);
+                cx.deadline_check(
+                    &cx.step_label(index, index),
+                    quote! {
+                        #on_none
+                        return None;
+                    },
+                )
+            } else {
+                quote! {}
+            }
+        };
+        let (last, alternatives) = steps.split_last().unwrap();
+        let attempts = alternatives.iter().enumerate().map(|(index, step)| {
+            let check = check(index + 1);
+            let value = cx.observe(quote! { value });
+            quote! {
+                #check
+                if let #success(value) = #step(Clone::clone(&seed)) {
+                    let value: #item = #value;
+                    #step_hooks
+                    return #success(value);
+                }
+            }
+        });
+        let index = steps.len();
+        let last_check = check(index);
+        let value = cx.observe(kind.unwrap_or_return(
+            quote! { #last(seed) },
+            cx.on_none(&index),
+        ));
+        let body = quote! {
+            #deadline_start
+            let seed: #item = #start;
+            #(#attempts)*
+            #last_check
+            let value: #item = #value;
+            #step_hooks
+            #success(value)
+        };
+        quote_spanned! { struct_name.span()=>
+            #[doc = #doc]
+            ///
+            /// Every step receives a clone of the original value rather than the previous
+            /// step's output; the run fails only if all of them fail, with the last failure.
+            #skip_doc
+            pub fn #name<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> #step_output
+            where
+                #(#step_types: FnOnce(#item) -> #step_output,)*
+            {
+                #timeout_code
+                #body
+            }
+        }
+    });
+    quote! { #(#methods)* }
+}
@@ -0,0 +1,301 @@
+use crate::context::Context;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{Ident, ext::IdentExt, parse_quote_spanned};
+
+/// What the timeout messages report for the step at `index`, an expression, of a run whose steps
+/// are only known at run time. With `step_names`, the steps are labelled by position, so the
+/// names only cover the first steps of a longer list.
+fn dynamic_label(cx: &Context, index: TokenStream) -> TokenStream {
+    match &cx.attrs.step_names {
+        Some(names) => {
+            let numbers = 1..=names.len();
+            quote! {
+                match #index {
+                    0 => "seed",
+                    #(#numbers => #names,)*
+                    _ => "unnamed",
+                }
+            }
+        }
+        None => index,
+    }
+}
+
+/// Builds a body folding `steps`, an iterator expression, over `seed` in a loop, for the methods
+/// whose steps are only known at run time. `binding` is how each step is bound.
+fn fold_steps(
+    cx: &Context,
+    steps: &Ident,
+    binding: TokenStream,
+    seed: &TokenStream,
+    eq_check: bool,
+) -> TokenStream {
+    let Context {
+        kind,
+        item,
+        success,
+        after_step,
+        trace_step,
+        ..
+    } = cx;
+    let start = kind.unwrap_or_return(seed, cx.on_none(&0usize));
+    let step = cx.observe(kind.unwrap_or_return(
+        cx.call_step(quote! { step }, dynamic_label(cx, quote! { index + 1 })),
+        cx.on_none(&quote! { index + 1 }),
+    ));
+    let on_expire = cx.on_none(&quote! { index + 1 });
+    // How many steps are left is not known up front, so the last one is compared too
+    let (keep_input, compare) = if eq_check {
+        (
+            quote! { let input: #item = Clone::clone(&value); },
+            quote! {
+                if value == input {
+                    break;
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    let check = cx.deadline_check(
+        &dynamic_label(cx, quote! { index + 1 }),
+        quote! {
+            #on_expire
+            return None;
+        },
+    );
+    let deadline_start = cx.deadline_start();
+    quote! {
+        #deadline_start
+        let mut value: #item = #start;
+        for (index, #binding) in #steps.into_iter().enumerate() {
+            if index > 0 {
+                #check
+            }
+            #keep_input
+            value = #step;
+            #after_step
+            #trace_step
+            #compare
+        }
+        #success(value)
+    }
+}
+
+/// With `version = 2`, `process` is the core method, taking any iterable of steps.
+pub fn process(cx: &Context) -> TokenStream {
+    if cx.fixed_arity {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        inline,
+        core_output,
+        core_panics,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let steps = Ident::new("steps", struct_name.span());
+    let process_body = cx.core_body(fold_steps(
+        cx,
+        &steps,
+        quote! { step },
+        &cx.chain_seed(),
+        cx.attrs.eq_check,
+    ));
+    let (iter, step_type) = (cx.method_param("I"), cx.method_param("F"));
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with each step in `steps`, in order, stopping at the
+        /// first failing step.
+        ///
+        /// All steps share one type, so pass e.g. function pointers or boxed closures.
+        #skip_doc
+        #core_panics
+        #inline
+        pub fn process<#item_lifetime #iter, #step_type>(#receiver, #steps: #iter) -> #core_output
+        where
+            #iter: IntoIterator<Item = #step_type>,
+            #step_type: FnOnce(#item) -> #step_output,
+        {
+            #timeout_code
+            #process_body
+        }
+    }
+}
+
+/// If `run_dyn` is set, take ownership of boxed `FnMut` steps, e.g. stateful closures assembled
+/// at run time. `and_then` needs `FnOnce`, so it shares the loop of `process`.
+pub fn run_dyn(cx: &Context) -> TokenStream {
+    if !cx.attrs.run_dyn {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let steps = Ident::new("steps", struct_name.span());
+    let body = fold_steps(cx, &steps, quote! { mut step }, &cx.chain_seed(), false);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with each boxed step in `steps`, in order, stopping at
+        /// the first failing step.
+        ///
+        /// Steps may be `FnMut`, so they can keep state across calls of the closure.
+        #skip_doc
+        pub fn run_dyn<#item_lifetime '__steps>(#receiver, #steps: Vec<Box<dyn FnMut(#item) -> #step_output + '__steps>>) -> #step_output {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// If `apply_all` is set, run one step `n` times, e.g. for iterative refinement, through the
+/// same loop.
+pub fn apply_all(cx: &Context) -> TokenStream {
+    if !cx.attrs.apply_all {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let steps = Ident::new("steps", struct_name.span());
+    let (step, count) = (
+        Ident::new("f", struct_name.span()),
+        Ident::new("n", struct_name.span()),
+    );
+    let step_type = cx.method_param("F");
+    let body = fold_steps(cx, &steps, quote! { step }, &cx.chain_seed(), false);
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value by applying `f` `n` times, stopping at the first failing
+        /// application. With `n == 0`, returns the stored value.
+        #skip_doc
+        pub fn apply_all<#item_lifetime #step_type>(#receiver, #step: #step_type, #count: usize) -> #step_output
+        where
+            #step_type: Fn(#item) -> #step_output,
+        {
+            let #steps = ::std::iter::repeat_n(&#step, #count);
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// If `config` is set, a `{Struct}Config` value gathers an optional seed and boxed steps, so a
+/// run can be assembled in one place and stored before `run` executes it like `run_dyn`.
+///
+/// Returns the `run` method and the config type with its impls.
+pub fn run_config(cx: &Context) -> (TokenStream, TokenStream) {
+    if !cx.attrs.config {
+        return (quote! {}, quote! {});
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let (receiver, chain_seed) = (cx.receiver(), cx.chain_seed());
+    let steps = Ident::new("steps", struct_name.span());
+    let config_name = format_ident!("{}Config", struct_name.unraw(), span = struct_name.span());
+    let config = Ident::new("config", struct_name.span());
+    let step_type = cx.method_param("F");
+    let vis = &cx.input.vis;
+    let mut config_generics = cx.helper_generics();
+    config_generics
+        .params
+        .insert(0, parse_quote_spanned! { struct_name.span()=> '__steps });
+    let (config_impl_generics, config_ty_generics, config_where_clause) =
+        config_generics.split_for_impl();
+    // A seed set on the config replaces the field's value, but is still subject to `skip` and
+    // `skip_if`
+    let seed = if cx.attrs.skip {
+        chain_seed.clone()
+    } else {
+        let config_seed = cx.skippable(quote! { seed }, inner_type);
+        quote! {
+            match #config.seed {
+                Some(seed) => #config_seed,
+                None => #chain_seed,
+            }
+        }
+    };
+    let body = fold_steps(cx, &steps, quote! { mut step }, &seed, false);
+    let doc = format!(
+        " Seed and steps of a run of [`{}::run`], generated by `#[pipeline(config)]`.",
+        cx.target().unraw()
+    );
+    let items = quote! {
+        #[doc = #doc]
+        #[allow(clippy::type_complexity)]
+        #vis struct #config_name #config_generics #config_where_clause {
+            seed: Option<#output>,
+            steps: Vec<Box<dyn FnMut(#inner_type) -> #output + '__steps>>,
+        }
+
+        #[allow(dead_code)]
+        impl #config_impl_generics #config_name #config_ty_generics #config_where_clause {
+            /// Creates a config without steps, starting from the struct's own value.
+            #vis fn new() -> Self {
+                Self {
+                    seed: None,
+                    steps: Vec::new(),
+                }
+            }
+
+            /// Starts the run from `seed` instead of the struct's own value.
+            #[must_use]
+            #vis fn seed(mut self, seed: #output) -> Self {
+                self.seed = Some(seed);
+                self
+            }
+
+            /// Appends `step` to the steps of the run.
+            #[must_use]
+            #vis fn step<#step_type>(mut self, step: #step_type) -> Self
+            where
+                #step_type: FnMut(#inner_type) -> #output + '__steps,
+            {
+                self.steps.push(Box::new(step));
+                self
+            }
+        }
+
+        impl #config_impl_generics Default for #config_name #config_ty_generics #config_where_clause {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+    let method = quote_spanned! { struct_name.span()=>
+        /// Processes the seed of `config`, or the inner value if it has none, with each of its
+        /// steps in order, stopping at the first failing step.
+        #skip_doc
+        pub fn run<'__steps>(#receiver, #config: #config_name #config_ty_generics) -> #step_output {
+            #timeout_code
+            let #steps = #config.steps;
+            #body
+        }
+    };
+    (method, items)
+}
@@ -0,0 +1,115 @@
+use crate::context::Context;
+use crate::pipeline::FieldKind;
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{Ident, ext::IdentExt};
+
+/// If `accessors` is set, generate helpers replacing the stored value.
+pub fn accessors(cx: &Context) -> TokenStream {
+    if !cx.attrs.accessors {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_place,
+        inner_type,
+        ..
+    } = cx;
+    let mut_receiver = cx.mut_receiver();
+    let value = Ident::new("value", struct_name.span());
+    quote_spanned! { struct_name.span()=>
+        /// Resets the pipeline field to `None`.
+        pub fn clear(#mut_receiver) {
+            #field_place = None;
+        }
+
+        /// Stores `value` in the pipeline field, replacing the previous value.
+        pub fn set(#mut_receiver, #value: #inner_type) {
+            #field_place = Some(#value);
+        }
+    }
+}
+
+/// If `peek` is set, borrow the stored value, ignoring `skip`, so callers can inspect it without
+/// cloning.
+pub fn peek(cx: &Context) -> TokenStream {
+    if !cx.attrs.peek {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_ident,
+        inner_type,
+        this,
+        ..
+    } = cx;
+    // With `namespace` the struct is an ordinary parameter, so the borrow is named: elision would
+    // not pick it over the struct's own lifetimes.
+    let (peek_lifetime, peek_ref) = if cx.attrs.namespace {
+        (quote! { <'__pipeline> }, quote! { &'__pipeline })
+    } else {
+        (quote! {}, quote! { & })
+    };
+    let peek_receiver = cx.struct_receiver(peek_ref.clone());
+    let peek_output = match cx.kind {
+        FieldKind::Option => quote! { ::core::option::Option<#peek_ref #inner_type> },
+        FieldKind::Result(err_type) => {
+            quote! { ::core::result::Result<#peek_ref #inner_type, #peek_ref #err_type> }
+        }
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Returns a reference to the stored value without cloning it or running any steps.
+        pub fn peek #peek_lifetime (#peek_receiver) -> #peek_output {
+            #this.#field_ident.as_ref()
+        }
+    }
+}
+
+/// If `into_parts` is set, move the stored value out, ignoring `skip`, so no `Clone` is needed.
+pub fn into_parts(cx: &Context) -> TokenStream {
+    if !cx.attrs.into_parts {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_ident,
+        output,
+        this,
+        ..
+    } = cx;
+    let owned_receiver = cx.struct_receiver(quote! {});
+    quote_spanned! { struct_name.span()=>
+        /// Consumes the struct and returns the stored value without cloning it or running any
+        /// steps.
+        pub fn into_parts(#owned_receiver) -> #output {
+            #this.#field_ident
+        }
+    }
+}
+
+/// If `step_names` is set, `steps` exposes them at run time, e.g. for tooling listing a
+/// pipeline's steps.
+pub fn steps_fn(cx: &Context) -> TokenStream {
+    let Some(names) = &cx.attrs.step_names else {
+        return quote! {};
+    };
+    quote_spanned! { cx.struct_name.span()=>
+        /// Returns the step names configured with `step_names`, in order.
+        pub fn steps() -> &'static [&'static str] {
+            &[#(#names),*]
+        }
+    }
+}
+
+/// If `field_name` is set, `FIELD_NAME` names the pipeline field for glue code, without the `r#`
+/// of a raw identifier.
+pub fn field_name_const(cx: &Context) -> TokenStream {
+    if !cx.attrs.field_name {
+        return quote! {};
+    }
+    let field_name = cx.field_ident.unraw().to_string();
+    quote_spanned! { cx.struct_name.span()=>
+        /// The name of the pipeline field, e.g. for serialization glue.
+        pub const FIELD_NAME: &'static str = #field_name;
+    }
+}
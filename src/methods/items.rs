@@ -0,0 +1,356 @@
+use crate::context::Context;
+use crate::pipeline::{FieldKind, count_word, step_params};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::ext::IdentExt;
+
+/// With `namespace`, the marker type the pipeline methods are declared on, carrying the struct's
+/// generics.
+pub fn marker_type(cx: &Context) -> TokenStream {
+    if !cx.attrs.namespace {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        marker,
+        ..
+    } = cx;
+    let vis = &cx.input.vis;
+    let generics = &cx.input.generics;
+    let (_, struct_ty_generics, struct_where_clause) = cx.input.generics.split_for_impl();
+    let doc = format!(
+        " Namespace holding the pipeline functions of [`{}`], generated by `#[pipeline(namespace)]`.",
+        struct_name.unraw()
+    );
+    quote_spanned! { struct_name.span()=>
+        #[doc = #doc]
+        #vis struct #marker #generics (::std::marker::PhantomData<fn() -> #struct_name #struct_ty_generics>) #struct_where_clause;
+    }
+}
+
+/// If `export_trait` is set, generate a named trait carrying the pipeline methods as default
+/// impls. The trait only requires a seed accessor, so other types can adopt the same interface.
+pub fn exported_trait(cx: &Context) -> TokenStream {
+    let Some(trait_name) = &cx.attrs.export_trait else {
+        return quote! {};
+    };
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        self_receiver,
+        rebind,
+        ..
+    } = cx;
+    let vis = &cx.input.vis;
+    let (impl_generics, ty_generics, where_clause) = cx.generics.split_for_impl();
+    let seed = cx.seed();
+    let trait_process = if cx.fixed_arity {
+        quote! {}
+    } else {
+        quote_spanned! { trait_name.span()=>
+            /// Processes the seed with each step in `steps`, in order.
+            fn process<I, F>(&self, steps: I) -> Option<Self::Item>
+            where
+                I: IntoIterator<Item = F>,
+                F: FnOnce(Self::Item) -> Option<Self::Item>,
+            {
+                steps.into_iter().fold(self.pipeline_seed(), |value, step| value.and_then(step))
+            }
+        }
+    };
+    let trait_fixed_arity = cx.arities.iter().filter(|_| cx.fixed_arity).map(|&arity| {
+        let name = format_ident!("process{}", arity);
+        let (steps, step_types) = step_params(arity - 1, trait_name.span());
+        let doc = format!(
+            " Processes the seed with {} chained closure steps.",
+            count_word(arity - 1)
+        );
+        quote_spanned! { trait_name.span()=>
+            #[doc = #doc]
+            fn #name<#(#step_types),*>(&self, #(#steps: #step_types),*) -> Option<Self::Item>
+            where
+                #(#step_types: FnOnce(Self::Item) -> Option<Self::Item>,)*
+            {
+                self.pipeline_seed()#(.and_then(#steps))*
+            }
+        }
+    });
+    quote_spanned! { trait_name.span()=>
+        /// Pipeline interface generated by `#[pipeline(export_trait = "...")]`.
+        #[allow(dead_code, clippy::too_many_arguments)]
+        #vis trait #trait_name {
+            /// Type of the value flowing through the pipeline.
+            type Item: Clone;
+
+            /// Returns the value the pipeline starts from.
+            fn pipeline_seed(&self) -> Option<Self::Item>;
+
+            #trait_process
+            #(#trait_fixed_arity)*
+        }
+
+        impl #impl_generics #trait_name for #struct_name #ty_generics #where_clause {
+            type Item = #inner_type;
+
+            fn pipeline_seed(#self_receiver) -> #output {
+                #rebind
+                #seed
+            }
+        }
+    }
+}
+
+/// The field built from a wrapper `value`, for the constructors of `serde` and `from_option`.
+fn from_inner(cx: &Context) -> TokenStream {
+    if cx.shared {
+        quote_spanned! { cx.struct_name.span()=> ::std::sync::Arc::new(::std::sync::Mutex::new(value)) }
+    } else {
+        quote_spanned! { cx.struct_name.span()=> value }
+    }
+}
+
+/// If `serde` is set, generate helpers exposing the field as the struct's transparent
+/// representation.
+pub fn serde_helpers(cx: &Context) -> TokenStream {
+    if !cx.attrs.serde {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_ident,
+        output,
+        hidden,
+        self_receiver,
+        rebind,
+        ..
+    } = cx;
+    let (impl_generics, ty_generics, where_clause) = cx.generics.split_for_impl();
+    let to_inner = cx.read_field(quote! { .clone() });
+    let from_inner = from_inner(cx);
+    // A field itself named `value` reads as `value: value`, which clippy's `redundant_field_names`
+    // flags; the shorthand is not an option, as the field name comes from the caller's tokens.
+    quote_spanned! { struct_name.span()=>
+        #hidden
+        #[allow(dead_code)]
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            /// Returns a clone of the inner field, e.g. for `#[serde(into = ...)]`-style serialization.
+            pub fn to_inner(#self_receiver) -> #output {
+                #rebind
+                #to_inner
+            }
+
+            /// Builds the struct from an inner field value, e.g. for `#[serde(from = ...)]`-style deserialization.
+            #[allow(clippy::redundant_field_names)]
+            pub fn from_inner(value: #output) -> Self {
+                Self { #field_ident: #from_inner }
+            }
+        }
+    }
+}
+
+/// If `from_option` is set, the struct converts from its field's wrapper value, so callers can
+/// write `Some(1).into()` or `P::from(None)`.
+pub fn from_impl(cx: &Context) -> TokenStream {
+    if !cx.attrs.from_option {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_ident,
+        output,
+        ..
+    } = cx;
+    let (impl_generics, ty_generics, where_clause) = cx.input.generics.split_for_impl();
+    let from_inner = from_inner(cx);
+    quote_spanned! { struct_name.span()=>
+        impl #impl_generics ::std::convert::From<#output> for #struct_name #ty_generics #where_clause {
+            #[allow(clippy::redundant_field_names)]
+            fn from(value: #output) -> Self {
+                Self { #field_ident: #from_inner }
+            }
+        }
+    }
+}
+
+/// The error enum returned by `try_process3`, declared next to the struct with its visibility.
+pub fn error_enum(cx: &Context) -> TokenStream {
+    let Some(error_enum) = &cx.attrs.error_enum else {
+        return quote! {};
+    };
+    let vis = &cx.input.vis;
+    quote_spanned! { error_enum.span()=>
+        /// Reason a pipeline run generated by `#[pipeline(error_enum = "...")]` stopped.
+        // Without a `timeout`, or with another `timeout_action`, no run returns `Timeout`
+        #[allow(dead_code)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #vis enum #error_enum {
+            /// The step at index `step` returned `None`; `0` means the field was `None`.
+            EarlyStop {
+                /// 1-based index of the failing step.
+                step: usize,
+            },
+            /// The `timeout` deadline passed before step `step` ran, under the default
+            /// `timeout_action = "none"`.
+            Timeout {
+                /// 1-based index of the step that was not run.
+                step: usize,
+            },
+        }
+
+        impl ::std::fmt::Display for #error_enum {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                match self {
+                    Self::EarlyStop { step: 0 } => f.write_str("pipeline has no value to process"),
+                    Self::EarlyStop { step } => write!(f, "pipeline stopped at step {}", step),
+                    Self::Timeout { step } => write!(f, "pipeline timed out before step {}", step),
+                }
+            }
+        }
+
+        impl ::std::error::Error for #error_enum {}
+    }
+}
+
+/// If `par_iter` is set, run the steps over each element of a `Vec` field on rayon's thread pool,
+/// keeping the elements that make it through every step.
+pub fn par_iter_method(cx: &Context) -> TokenStream {
+    let Some(element) = cx.par_iter_element else {
+        return quote! {};
+    };
+    let Context {
+        struct_name,
+        skip_doc,
+        timeout_code,
+        hidden,
+        self_receiver,
+        rebind,
+        ..
+    } = cx;
+    let (impl_generics, ty_generics, where_clause) = cx.generics.split_for_impl();
+    let seed = cx.seed();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    quote_spanned! { struct_name.span()=>
+        #hidden
+        #[allow(dead_code)]
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            /// Runs two chained closure steps over every element of the inner `Vec` in
+            /// parallel, collecting the elements for which both steps succeed, in order.
+            #skip_doc
+            pub fn process3_par_iter<#(#step_types),*>(#self_receiver, #(#steps: #step_types),*) -> Option<Vec<#element>>
+            where
+                #element: Clone + Send + Sync,
+                #(#step_types: Fn(#element) -> Option<#element> + Send + Sync,)*
+            {
+                use ::rayon::prelude::*;
+                #rebind
+                #timeout_code
+                #seed.map(|items| {
+                    items
+                        .par_iter()
+                        .filter_map(|item| Some(Clone::clone(item)) #(.and_then(&#steps))*)
+                        .collect()
+                })
+            }
+        }
+    }
+}
+
+/// If `assert_send` is set, require `Struct: Send` in a never-called function, which also works
+/// for generic structs by checking under their declared bounds. Taking the struct as a parameter
+/// gives the function the struct's implied bounds, such as `T: 'a` for `&'a T`.
+pub fn send_assertion(cx: &Context) -> TokenStream {
+    if !cx.attrs.assert_send {
+        return quote! {};
+    }
+    let struct_name = cx.struct_name;
+    let (impl_generics, ty_generics, where_clause) = cx.input.generics.split_for_impl();
+    quote_spanned! { struct_name.span()=>
+        const _: () = {
+            fn assert_send<T: ?Sized + Send>() {}
+            #[allow(dead_code)]
+            fn assert_struct_is_send #impl_generics (_: &#struct_name #ty_generics) #where_clause {
+                assert_send::<#struct_name #ty_generics>();
+            }
+        };
+    }
+}
+
+/// If `derive_debug` is set, implement `Debug` reporting only whether the field holds a value, so
+/// it needs no `T: Debug` bound.
+pub fn debug_impl(cx: &Context) -> TokenStream {
+    if !cx.attrs.derive_debug {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_ident,
+        field_place,
+        self_receiver,
+        rebind,
+        ..
+    } = cx;
+    let (impl_generics, ty_generics, where_clause) = cx.input.generics.split_for_impl();
+    let name = struct_name.unraw().to_string();
+    let field_name = field_ident.unraw().to_string();
+    let states = match cx.kind {
+        FieldKind::Option => quote! {
+            Some(_) => "Some(..)",
+            None => "None",
+        },
+        FieldKind::Result(_) => quote! {
+            Ok(_) => "Ok(..)",
+            Err(_) => "Err(..)",
+        },
+    };
+    quote_spanned! { struct_name.span()=>
+        impl #impl_generics ::std::fmt::Debug for #struct_name #ty_generics #where_clause {
+            fn fmt(#self_receiver, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                #rebind
+                let state: &str = match &#field_place {
+                    #states
+                };
+                f.debug_struct(#name)
+                    .field(#field_name, &format_args!("{}", state))
+                    .finish()
+            }
+        }
+    }
+}
+
+/// If `on_none` is set, declare the hidden `{Struct}OnNone` trait the runs call the hook
+/// through. It is implemented for `Fn()` and `Fn(usize)`, so the hook may take the index of the
+/// failing step or ignore it.
+pub fn on_none_items(cx: &Context) -> TokenStream {
+    if cx.attrs.on_none.is_none() || cx.attrs.skip {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        on_none_trait,
+        ..
+    } = cx;
+    quote_spanned! { struct_name.span()=>
+        /// Calls an `on_none` hook taking either nothing or the failing step's index.
+        #[doc(hidden)]
+        #[diagnostic::on_unimplemented(
+            message = "`on_none` hook `{Self}` must be callable as `fn()` or `fn(usize)`",
+            label = "expected a hook taking nothing or the failing step's index"
+        )]
+        trait #on_none_trait<Args> {
+            fn call(&self, index: usize);
+        }
+
+        impl<F: Fn() -> R, R> #on_none_trait<fn() -> R> for F {
+            fn call(&self, _: usize) {
+                self();
+            }
+        }
+
+        impl<F: Fn(usize) -> R, R> #on_none_trait<fn(usize) -> R> for F {
+            fn call(&self, index: usize) {
+                self(index);
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use crate::attributes::PipelineMode;
+use crate::context::Context;
+use crate::pipeline::step_params;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{GenericParam, WherePredicate, ext::IdentExt, parse_quote_spanned, spanned::Spanned};
+
+/// If `generator` is set, expose the run as a `gen` block yielding the value after each
+/// successful step. The seed is cloned up front, so the iterator does not borrow `self`.
+pub fn process3_gen(cx: &Context) -> TokenStream {
+    if !cx.attrs.generator || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        kind,
+        inner_type,
+        output,
+        trace_owned,
+        struct_params,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let ref_receiver = cx.ref_receiver();
+    let seed = cx.seed();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let on_seed_none = cx.on_none(&0usize);
+    let start = kind.unwrap_or_else(
+        quote! { seed },
+        quote! {
+            #on_seed_none
+            return;
+        },
+    );
+    let bindings = steps.iter().enumerate().map(|(index, step)| {
+        let index = index + 1;
+        let on_none = cx.on_none(&index);
+        // Later steps consume `value`, so only the last yield can take it by move
+        let yielded = if index == steps.len() {
+            quote! { value }
+        } else {
+            quote! { Clone::clone(&value) }
+        };
+        let value = cx.observe(kind.unwrap_or_else(
+            cx.call_step(quote! { #step }, cx.step_label(index, index)),
+            quote! {
+                #on_none
+                return;
+            },
+        ));
+        quote! {
+            let value: #inner_type = #value;
+            #trace_owned
+            yield #yielded;
+        }
+    });
+    let body = quote! {
+        let seed: #output = #seed;
+        gen move {
+            let value: #inner_type = #start;
+            #(#bindings)*
+        }
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Returns an iterator running two chained closure steps lazily, yielding the value
+        /// produced by each successful step and ending at the first failure.
+        #skip_doc
+        pub fn process3_gen<#(#step_types),*>(#ref_receiver, #(#steps: #step_types),*) -> impl Iterator<Item = #inner_type> + use<#(#struct_params,)* #(#step_types),*>
+        where
+            #(#step_types: FnOnce(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// With `mode = "iterator"`, `process3_iter` returns a `{Struct}Steps` value driving one step per
+/// `next` call, a stable counterpart to `process3_gen`. The type carries the struct's generics so
+/// its fields can name the inner and wrapper types.
+///
+/// Returns the method and the iterator type with its impl.
+pub fn process3_iter(cx: &Context) -> (TokenStream, TokenStream) {
+    if cx.attrs.mode != Some(PipelineMode::Iterator) || !cx.steps_allowed(2) {
+        return (quote! {}, quote! {});
+    }
+    let Context {
+        struct_name,
+        kind,
+        inner_type,
+        output,
+        trace_owned,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let (ref_receiver, seed) = (cx.ref_receiver(), cx.seed());
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let iter_name = format_ident!("{}Steps", struct_name.unraw(), span = struct_name.span());
+    let vis = &cx.input.vis;
+    let mut iter_generics = cx.helper_generics();
+    iter_generics
+        .params
+        .extend(step_types.iter().map(|ty| -> GenericParam {
+            parse_quote_spanned! { ty.span()=> #ty }
+        }));
+    let (iter_impl_generics, iter_ty_generics, iter_where_clause) = iter_generics.split_for_impl();
+    let mut bounds: Vec<WherePredicate> = cx
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter().cloned())
+        .collect();
+    bounds.extend(step_types.iter().map(|ty| -> WherePredicate {
+        parse_quote_spanned! { ty.span()=> #ty: FnOnce(#inner_type) -> #output }
+    }));
+    let on_seed_none = cx.on_none(&0usize);
+    let start = kind.unwrap_or_else(
+        quote! { seed },
+        quote! {
+            #on_seed_none
+            return None;
+        },
+    );
+    let branches = (0..steps.len()).map(|index| {
+        let slot = syn::Index::from(index);
+        let number = index + 1;
+        let label = cx.step_label(number, number);
+        let on_none = cx.on_none(&number);
+        let value = cx.observe(kind.unwrap_or_else(
+            cx.call_step(quote! { step }, label),
+            quote! {
+                #on_none
+                return None;
+            },
+        ));
+        // The last step's value is handed out by move, as no later step needs it
+        let store = if number < steps.len() {
+            quote! { self.value = Some(Clone::clone(&value)); }
+        } else {
+            quote! {}
+        };
+        quote! {
+            if let Some(step) = self.steps.#slot.take() {
+                let value: #inner_type = #value;
+                #trace_owned
+                #store
+                return Some(value);
+            }
+        }
+    });
+    let doc = format!(
+        " Iterator returned by [`{}::process3_iter`], running one step per `next` call.",
+        cx.target().unraw()
+    );
+    let items = quote! {
+        #[doc = #doc]
+        #[must_use = "iterators are lazy and do nothing unless consumed"]
+        #vis struct #iter_name #iter_generics #iter_where_clause {
+            seed: Option<#output>,
+            value: Option<#inner_type>,
+            steps: (#(Option<#step_types>,)*),
+        }
+
+        impl #iter_impl_generics Iterator for #iter_name #iter_ty_generics
+        where
+            #(#bounds,)*
+        {
+            type Item = #inner_type;
+
+            fn next(&mut self) -> Option<#inner_type> {
+                if let Some(seed) = self.seed.take() {
+                    self.value = Some(#start);
+                }
+                let value = self.value.take()?;
+                #(#branches)*
+                None
+            }
+        }
+    };
+    let method = quote_spanned! { struct_name.span()=>
+        /// Returns an iterator running two chained closure steps lazily, one per `next` call,
+        /// yielding the value produced by each successful step and ending at the first
+        /// failure.
+        #skip_doc
+        pub fn process3_iter<#(#step_types),*>(#ref_receiver, #(#steps: #step_types),*) -> #iter_name #iter_ty_generics
+        where
+            #(#step_types: FnOnce(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            #iter_name {
+                seed: Some(#seed),
+                value: None,
+                steps: (#(Some(#steps),)*),
+            }
+        }
+    };
+    (method, items)
+}
+
+/// With `mode = "stepper"`, `process3_stepper` returns a `{Struct}Stepper` for callers driving
+/// the run themselves, e.g. from a custom executor: every `step` call runs one step and returns a
+/// `{Struct}State`, which stays `Pending` until the run has finished.
+///
+/// Returns the method and the stepper and state types with their impl.
+pub fn process3_stepper(cx: &Context) -> (TokenStream, TokenStream) {
+    if cx.attrs.mode != Some(PipelineMode::Stepper) || !cx.steps_allowed(2) {
+        return (quote! {}, quote! {});
+    }
+    let Context {
+        struct_name,
+        kind,
+        inner_type,
+        output,
+        success,
+        trace_owned,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let (ref_receiver, seed) = (cx.ref_receiver(), cx.seed());
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let stepper_name = format_ident!("{}Stepper", struct_name.unraw(), span = struct_name.span());
+    let state_name = format_ident!("{}State", struct_name.unraw(), span = struct_name.span());
+    let vis = &cx.input.vis;
+    let helper_generics = cx.helper_generics();
+    let (_, state_ty_generics, state_where_clause) = helper_generics.split_for_impl();
+    let mut stepper_generics = helper_generics.clone();
+    stepper_generics
+        .params
+        .extend(step_types.iter().map(|ty| -> GenericParam {
+            parse_quote_spanned! { ty.span()=> #ty }
+        }));
+    let (stepper_impl_generics, stepper_ty_generics, stepper_where_clause) =
+        stepper_generics.split_for_impl();
+    let mut bounds: Vec<WherePredicate> = cx
+        .generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter().cloned())
+        .collect();
+    bounds.extend(step_types.iter().map(|ty| -> WherePredicate {
+        parse_quote_spanned! { ty.span()=> #ty: FnOnce(#inner_type) -> #output }
+    }));
+    let (failure_pattern, failure) = (kind.failure_pattern(), kind.failure(true));
+    let on_seed_none = cx.on_none(&0usize);
+    let branches = (0..steps.len()).map(|index| {
+        let slot = syn::Index::from(index);
+        let number = index + 1;
+        let label = cx.step_label(number, number);
+        let on_none = cx.on_none(&number);
+        let result = cx.observe(cx.call_step(quote! { step }, label));
+        // The last step's value is handed out by move, as no later step needs it
+        let finish = if number < steps.len() {
+            quote! {
+                self.value = Some(value);
+                #state_name::Pending
+            }
+        } else {
+            quote! { #state_name::Done(#success(value)) }
+        };
+        quote! {
+            if let Some(step) = self.steps.#slot.take() {
+                return match #result {
+                    #success(value) => {
+                        #trace_owned
+                        #finish
+                    }
+                    #failure_pattern => {
+                        #on_none
+                        #state_name::Done(#failure)
+                    }
+                };
+            }
+        }
+    });
+    let state_doc = format!(
+        " Progress of a [`{}`] run, returned by each of its `step` calls.",
+        stepper_name.unraw()
+    );
+    let stepper_doc = format!(
+        " Stepper returned by [`{}::process3_stepper`], running one step per `step` call.",
+        cx.target().unraw()
+    );
+    let items = quote! {
+        #[doc = #state_doc]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis enum #state_name #helper_generics #state_where_clause {
+            /// The run has steps left; call `step` again to continue it.
+            Pending,
+            /// The run has finished with this result.
+            Done(#output),
+        }
+
+        #[doc = #stepper_doc]
+        #[must_use = "steppers do nothing unless `step` is called"]
+        #vis struct #stepper_name #stepper_generics #stepper_where_clause {
+            seed: Option<#output>,
+            value: Option<#inner_type>,
+            steps: (#(Option<#step_types>,)*),
+        }
+
+        #[allow(dead_code)]
+        impl #stepper_impl_generics #stepper_name #stepper_ty_generics
+        where
+            #(#bounds,)*
+        {
+            /// Runs the next step, returning `Pending` while steps are left and `Done` with
+            /// the result once the last step ran or a step failed.
+            ///
+            /// # Panics
+            ///
+            /// Panics if called again after returning `Done`.
+            #vis fn step(&mut self) -> #state_name #state_ty_generics {
+                if let Some(seed) = self.seed.take() {
+                    match seed {
+                        #success(value) => self.value = Some(value),
+                        #failure_pattern => {
+                            #on_seed_none
+                            return #state_name::Done(#failure);
+                        }
+                    }
+                }
+                let value = self
+                    .value
+                    .take()
+                    .expect("`step` called after the pipeline finished");
+                #(#branches)*
+                unreachable!("a pending pipeline has a step left")
+            }
+        }
+    };
+    let method = quote_spanned! { struct_name.span()=>
+        /// Returns a stepper running two chained closure steps, one per `step` call, so the
+        /// caller can interleave the run with other work.
+        #skip_doc
+        pub fn process3_stepper<#(#step_types),*>(#ref_receiver, #(#steps: #step_types),*) -> #stepper_name #stepper_ty_generics
+        where
+            #(#step_types: FnOnce(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            #stepper_name {
+                seed: Some(#seed),
+                value: None,
+                steps: (#(Some(#steps),)*),
+            }
+        }
+    };
+    (method, items)
+}
@@ -0,0 +1,16 @@
+//! Generators of the pipeline methods and items, one function per generated method.
+//!
+//! Each generator reads what it needs from the [`Context`](crate::context::Context) and returns
+//! the method's tokens, or nothing if its attributes do not ask for it. `pipeline_derive`
+//! places the methods in impl blocks; `items` generates the traits, types and impls declared next
+//! to the struct.
+
+pub mod adapters;
+pub mod arity;
+pub mod dynamic;
+pub mod fields;
+pub mod items;
+pub mod lazy;
+pub mod recorded;
+pub mod reported;
+pub mod seeded;
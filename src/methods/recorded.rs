@@ -0,0 +1,408 @@
+use crate::context::Context;
+use crate::pipeline::step_params;
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote, quote_spanned};
+use syn::Ident;
+
+/// An owned copy of a step's `value`, for recording it while the run goes on.
+fn owned(cx: &Context) -> TokenStream {
+    if cx.attrs.cow {
+        quote! { ::std::borrow::Cow::into_owned(value.clone()) }
+    } else {
+        // Spelled out so an item type like `&T` clones the reference rather than `T`
+        quote! { Clone::clone(&value) }
+    }
+}
+
+/// Builds a body running `steps` that pushes `entry(index)` onto a `trace` of `element`s after
+/// each successful step, then evaluates `finish` on the trace, also when a step fails.
+fn recorded_run(
+    cx: &Context,
+    steps: &[Ident],
+    element: TokenStream,
+    entry: &dyn Fn(usize) -> TokenStream,
+    finish: TokenStream,
+) -> TokenStream {
+    let Context {
+        kind,
+        item,
+        after_step,
+        trace_step,
+        ..
+    } = cx;
+    let stop = |index: usize| {
+        let on_none = cx.on_none(&index);
+        quote! {
+            #on_none
+            return #finish;
+        }
+    };
+    let start = kind.unwrap_or_else(cx.chain_seed(), stop(0));
+    let bindings = steps.iter().enumerate().map(|(index, step)| {
+        let index = index + 1;
+        let label = cx.step_label(index, index);
+        let value = cx.observe(
+            kind.unwrap_or_else(cx.call_step(quote! { #step }, label.clone()), stop(index)),
+        );
+        let check = if index > 1 {
+            cx.deadline_check(&label, stop(index))
+        } else {
+            quote! {}
+        };
+        let entry = entry(index);
+        quote! {
+            #check
+            let value: #item = #value;
+            #after_step
+            #trace_step
+            trace.push(#entry);
+        }
+    });
+    let deadline_start = cx.deadline_start();
+    quote! {
+        #deadline_start
+        let mut trace: Vec<#element> = Vec::new();
+        let value: #item = #start;
+        #(#bindings)*
+        #finish
+    }
+}
+
+/// `process3_collect` gathers the value produced by each successful step into any
+/// `FromIterator` type once the run ends, whether it completed or stopped early.
+pub fn process3_collect(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let collection = cx.method_param("C");
+    let owned = owned(cx);
+    let body = recorded_run(
+        cx,
+        &steps,
+        inner_type.to_token_stream(),
+        &|_| owned.clone(),
+        quote! { trace.into_iter().collect() },
+    );
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, collecting the value
+        /// produced by each successful step into `C`.
+        ///
+        /// A failing step ends the run early; the values produced before it are still
+        /// collected.
+        #skip_doc
+        pub fn process3_collect<#item_lifetime #(#step_types,)* #collection>(#receiver, #(#steps: #step_types),*) -> #collection
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+            #collection: FromIterator<#inner_type>,
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_trace` records like `process3_collect`, pairing each value with the 1-based index
+/// of the step that produced it and, with `step_names`, that step's name.
+pub fn process3_trace(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let owned = owned(cx);
+    let (element, entry): (_, Box<dyn Fn(usize) -> TokenStream>) = match &cx.attrs.step_names {
+        Some(_) => (
+            quote! { (usize, &'static str, #inner_type) },
+            Box::new(|index| {
+                let name = cx.step_label(index, index);
+                quote! { (#index, #name, #owned) }
+            }),
+        ),
+        None => (
+            quote! { (usize, #inner_type) },
+            Box::new(|index| quote! { (#index, #owned) }),
+        ),
+    };
+    let body = recorded_run(cx, &steps, element.clone(), &*entry, quote! { trace });
+    let named = if cx.attrs.step_names.is_some() {
+        quote! { #[doc = " Each entry also carries the step's name from `step_names`."] }
+    } else {
+        quote! {}
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning the value
+        /// produced by each successful step together with the step's 1-based index.
+        ///
+        /// A failing step ends the run early; the entries recorded before it are kept.
+        #named
+        #skip_doc
+        pub fn process3_trace<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> Vec<#element>
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_history` records every successful step's value like `process3_collect`, but only
+/// hands the history out together with the final value once the whole run has succeeded.
+pub fn process3_history(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        kind,
+        inner_type,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let owned = owned(cx);
+    let step_hooks = cx.step_hooks();
+    let record = quote! {
+        #step_hooks
+        history.push(#owned);
+    };
+    let run = cx.chain_from(&cx.chain_seed(), item, &record, &steps);
+    let body = quote! {
+        let mut history: Vec<#inner_type> = Vec::new();
+        let run = || -> #step_output { #run };
+        run().map(|value| (value, history))
+    };
+    let output = kind.wrap(quote! { (#item, Vec<#inner_type>) });
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning the final value
+        /// together with the value produced by each step, in order.
+        ///
+        /// A failing run returns the failure alone and discards the values recorded so far.
+        #skip_doc
+        pub fn process3_history<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> #output
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_tuple` returns the value after each step, so callers can inspect intermediate
+/// results; once a step fails, its slot and every later one hold the failure.
+pub fn process3_tuple(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        kind,
+        item,
+        item_lifetime,
+        step_output,
+        success,
+        after_step,
+        trace_step,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let outputs: Vec<Ident> = (1..=steps.len())
+        .map(|step| format_ident!("output{}", step))
+        .collect();
+    let pattern = kind.failure_pattern();
+    // Tuple returned when the step at `index` (0 for the seed) fails
+    let fail_at = |index: usize| {
+        let succeeded = outputs[..index.saturating_sub(1)].iter();
+        let failed = (index.max(1)..=steps.len()).map(|step| kind.failure(step == steps.len()));
+        let on_none = cx.on_none(&index);
+        quote! {
+            #on_none
+            return (#(#success(#succeeded),)* #(#failed,)*);
+        }
+    };
+    let start = fail_at(0);
+    let bindings = steps
+        .iter()
+        .zip(&outputs)
+        .enumerate()
+        .map(|(index, (step, output))| {
+            let fail = fail_at(index + 1);
+            let check = if index > 0 {
+                let step_index = index + 1;
+                cx.deadline_check(&cx.step_label(step_index, step_index), fail_at(step_index))
+            } else {
+                quote! {}
+            };
+            let call = cx.call_step(quote! { #step }, cx.step_label(index + 1, index + 1));
+            let value = cx.observe(quote! {
+                match #call {
+                    #success(value) => value,
+                    #pattern => {
+                        #fail
+                    }
+                }
+            });
+            // Later steps consume `value`, so only the last output can take it by move
+            let keep = if index + 1 == steps.len() {
+                quote! { value }
+            } else {
+                quote! { Clone::clone(&value) }
+            };
+            quote! {
+                #check
+                let value: #item = #value;
+                #after_step
+                #trace_step
+                let #output: #item = #keep;
+            }
+        });
+    let chain_seed = cx.chain_seed();
+    let deadline_start = cx.deadline_start();
+    let body = quote! {
+        #deadline_start
+        let value: #item = match #chain_seed {
+            #success(value) => value,
+            #pattern => {
+                #start
+            }
+        };
+        #(#bindings)*
+        (#(#success(#outputs),)*)
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, returning the value
+        /// after `f1` and after `f2`.
+        ///
+        /// If a step fails, its slot and all later ones hold the failure.
+        #skip_doc
+        pub fn process3_tuple<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> (#step_output, #step_output)
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_with_count` also reports how many steps ran, counting the one that failed; as every
+/// exit point knows its position, the counts are literals rather than a counter.
+pub fn process3_with_count(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        kind,
+        item,
+        item_lifetime,
+        step_output,
+        success,
+        after_step,
+        trace_step,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let pattern = kind.failure_pattern();
+    let failure = kind.failure(true);
+    let exit = |index: usize, executed: usize| {
+        let on_none = cx.on_none(&index);
+        quote! {
+            #on_none
+            return (#failure, #executed);
+        }
+    };
+    let start = exit(0, 0);
+    let bindings = steps.iter().enumerate().map(|(index, step)| {
+        let index = index + 1;
+        let fail = exit(index, index);
+        let check = if index > 1 {
+            let on_none = cx.on_none(&index);
+            let executed = index - 1;
+            cx.deadline_check(
+                &cx.step_label(index, index),
+                quote! {
+                    #on_none
+                    return (None, #executed);
+                },
+            )
+        } else {
+            quote! {}
+        };
+        let call = cx.call_step(quote! { #step }, cx.step_label(index, index));
+        let value = cx.observe(quote! {
+            match #call {
+                #success(value) => value,
+                #pattern => {
+                    #fail
+                }
+            }
+        });
+        quote! {
+            #check
+            let value: #item = #value;
+            #after_step
+            #trace_step
+        }
+    });
+    let executed = steps.len();
+    let chain_seed = cx.chain_seed();
+    let deadline_start = cx.deadline_start();
+    let body = quote! {
+        #deadline_start
+        let value: #item = match #chain_seed {
+            #success(value) => value,
+            #pattern => {
+                #start
+            }
+        };
+        #(#bindings)*
+        (#success(value), #executed)
+    };
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, also returning how many
+        /// steps ran, including a failing one.
+        #skip_doc
+        pub fn process3_with_count<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types),*) -> (#step_output, usize)
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
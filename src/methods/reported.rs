@@ -0,0 +1,149 @@
+use crate::context::Context;
+use crate::pipeline::{FieldKind, step_params};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::Ident;
+
+/// Builds a body chaining `steps` that reports every step boundary. `report` turns the format
+/// arguments of a message into the statement delivering it.
+fn reported_run(
+    cx: &Context,
+    steps: &[Ident],
+    report: &dyn Fn(TokenStream) -> TokenStream,
+) -> TokenStream {
+    let Context {
+        kind,
+        item,
+        success,
+        ..
+    } = cx;
+    let (empty, failed) = match kind {
+        FieldKind::Option => ("no value to process", "returned None"),
+        FieldKind::Result(_) => ("field holds an error", "returned an error"),
+    };
+    let on_seed_none = cx.on_none(&0usize);
+    let report_empty = report(quote! { #empty });
+    let start = kind.unwrap_or_return(
+        cx.chain_seed(),
+        quote! {
+            #on_seed_none
+            #report_empty
+        },
+    );
+    let step_hooks = cx.step_hooks();
+    let bindings = steps.iter().enumerate().map(|(index, step)| {
+        let index = index + 1;
+        let label = cx.step_label(index, index);
+        let on_none = cx.on_none(&index);
+        let report_failed = report(quote! { "step {} {}", #label, #failed });
+        let value = cx.observe(kind.unwrap_or_return(
+            cx.call_step(quote! { #step }, label.clone()),
+            quote! {
+                #on_none
+                #report_failed
+            },
+        ));
+        let check = if index > 1 {
+            let report_expired = report(quote! { "step {} skipped: timeout exceeded", #label });
+            cx.deadline_check(
+                &label,
+                quote! {
+                    #on_none
+                    #report_expired
+                    return None;
+                },
+            )
+        } else {
+            quote! {}
+        };
+        let report_succeeded = report(quote! { "step {} succeeded", #label });
+        quote! {
+            #check
+            let value: #item = #value;
+            #step_hooks
+            #report_succeeded
+        }
+    });
+    let deadline_start = cx.deadline_start();
+    quote! {
+        #deadline_start
+        let value: #item = #start;
+        #(#bindings)*
+        #success(value)
+    }
+}
+
+/// `process3_diag` reports every step boundary to a caller-supplied `Vec<String>`, giving a
+/// per-call trace without a global logger.
+pub fn process3_diag(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let diag = Ident::new("diag", struct_name.span());
+    let body = reported_run(cx, &steps, &|message| {
+        quote! { #diag.push(format!(#message)); }
+    });
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, pushing a message to
+        /// `diag` at every step boundary, e.g. "step 1 succeeded" or "step 2 returned None".
+        #skip_doc
+        pub fn process3_diag<#item_lifetime #(#step_types),*>(#receiver, #(#steps: #step_types,)* #diag: &mut Vec<String>) -> #step_output
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `process3_to_writer` writes the messages of `process3_diag` as lines to any `io::Write` sink.
+/// Write errors are ignored: the sink only observes the run, so a failing one must not change its
+/// result.
+pub fn process3_to_writer(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        item,
+        item_lifetime,
+        step_output,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let receiver = cx.receiver();
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let writer = Ident::new("writer", struct_name.span());
+    let writer_type = cx.method_param("W");
+    let body = reported_run(cx, &steps, &|message| {
+        quote! { let _ = ::std::writeln!(#writer, #message); }
+    });
+    quote_spanned! { struct_name.span()=>
+        /// Processes the inner value with two chained closure steps, writing a line to
+        /// `writer` at every step boundary, e.g. "step 1 succeeded" or "step 2 returned None".
+        ///
+        /// Write errors are ignored, so a failing writer never changes the result.
+        #skip_doc
+        pub fn process3_to_writer<#item_lifetime #(#step_types,)* #writer_type>(#receiver, #(#steps: #step_types,)* #writer: &mut #writer_type) -> #step_output
+        where
+            #(#step_types: FnOnce(#item) -> #step_output,)*
+            #writer_type: ::std::io::Write + ?Sized,
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
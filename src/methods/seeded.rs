@@ -0,0 +1,264 @@
+use crate::context::Context;
+use crate::pipeline::{count_word, step_params};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::Ident;
+
+/// The `start` parameter of the methods taking a caller-supplied seed, and the seed built from
+/// it.
+fn from_seed(cx: &Context) -> (Ident, TokenStream) {
+    let start = Ident::new("start", cx.struct_name.span());
+    let seed = if cx.attrs.skip {
+        let inner_type = cx.inner_type;
+        quote! { { let _ = #start; None::<#inner_type> } }
+    } else {
+        let success = &cx.success;
+        cx.skippable(quote! { #success(#start) }, cx.inner_type)
+    };
+    (start, seed)
+}
+
+/// If `both_receivers` is set, also generate consuming `into_*` variants that move the field out
+/// instead of cloning it.
+pub fn consuming_methods(cx: &Context) -> TokenStream {
+    if !cx.attrs.both_receivers {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        field_ident,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        skip_doc,
+        timeout_code,
+        this,
+        ..
+    } = cx;
+    let owned_receiver = cx.struct_receiver(quote! {});
+    let into_seed = if cx.attrs.skip {
+        quote! { None::<#inner_type> }
+    } else {
+        cx.skippable(quote! { #this.#field_ident }, inner_type)
+    };
+    let into_methods = cx.arities.iter().map(|&arity| {
+        let name = format_ident!("into_process{}", arity);
+        let (steps, step_types) = step_params(arity - 1, struct_name.span());
+        let doc = format!(
+            " Consumes the struct and processes its value with {} chained closure steps,",
+            count_word(arity - 1)
+        );
+        let body = cx.chain_from(&into_seed, inner_item, trace_owned, &steps);
+        quote_spanned! { struct_name.span()=>
+            #[doc = #doc]
+            /// without cloning.
+            #skip_doc
+            pub fn #name<#(#step_types),*>(#owned_receiver, #(#steps: #step_types),*) -> #output
+            where
+                #(#step_types: FnOnce(#inner_type) -> #output,)*
+            {
+                #timeout_code
+                #body
+            }
+        }
+    });
+    quote! {
+        #(#into_methods)*
+    }
+}
+
+/// If `take` is set, `process3_take` moves the value out of the field, leaving `None`, so a
+/// one-shot pipeline drains the struct without cloning or consuming it.
+pub fn take_method(cx: &Context) -> TokenStream {
+    if !cx.attrs.take || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let mut_receiver = cx.mut_receiver();
+    let take_seed = if cx.attrs.skip {
+        quote! { None::<#inner_type> }
+    } else {
+        cx.skippable(cx.read_field(quote! { .take() }), inner_type)
+    };
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let body = cx.chain_from(&take_seed, inner_item, trace_owned, &steps);
+    quote_spanned! { struct_name.span()=>
+        /// Takes the value out of the field, leaving `None`, and processes it with two
+        /// chained closure steps without cloning.
+        #skip_doc
+        pub fn process3_take<#(#step_types),*>(#mut_receiver, #(#steps: #step_types),*) -> #output
+        where
+            #(#step_types: FnOnce(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            #body
+        }
+    }
+}
+
+/// `*_from` variants run the same pipeline shape from a caller-supplied seed, ignoring the
+/// field's stored value.
+pub fn from_methods(cx: &Context) -> TokenStream {
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let ref_receiver = cx.ref_receiver();
+    let (start, from_seed) = from_seed(cx);
+    let from_methods = cx.arities.iter().map(|&arity| {
+        let name = format_ident!("process{}_from", arity);
+        let (steps, step_types) = step_params(arity - 1, struct_name.span());
+        let doc = format!(
+            " Processes `start` instead of the stored value with {} chained closure steps.",
+            count_word(arity - 1)
+        );
+        let body = cx.chain_from(&from_seed, inner_item, trace_owned, &steps);
+        quote_spanned! { struct_name.span()=>
+            #[doc = #doc]
+            #skip_doc
+            pub fn #name<#(#step_types),*>(#ref_receiver, #start: #inner_type, #(#steps: #step_types),*) -> #output
+            where
+                #(#step_types: FnOnce(#inner_type) -> #output,)*
+            {
+                #timeout_code
+                #body
+            }
+        }
+    });
+    quote! {
+        #(#from_methods)*
+    }
+}
+
+/// If `as_fn` is set, package two steps into a reusable closure over caller-supplied values,
+/// built like the `*_from` variants, for use with iterator adapters such as `filter_map`.
+pub fn as_fn(cx: &Context) -> TokenStream {
+    if !cx.attrs.as_fn || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        skip_doc,
+        struct_params,
+        timeout_code,
+        ..
+    } = cx;
+    let ref_receiver = cx.ref_receiver();
+    let (start, from_seed) = from_seed(cx);
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let body = cx.chain_from(&from_seed, inner_item, trace_owned, &steps);
+    quote_spanned! { struct_name.span()=>
+        /// Returns a closure running `f1` then `f2` on the value it is called with, ignoring
+        /// the stored value, e.g. for `values.into_iter().filter_map(p.as_fn(f1, f2))`.
+        #skip_doc
+        pub fn as_fn<#(#step_types),*>(#ref_receiver, #(#steps: #step_types),*) -> impl Fn(#inner_type) -> #output + use<#(#struct_params,)* #(#step_types),*>
+        where
+            #(#step_types: Fn(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            move |#start: #inner_type| -> #output { #body }
+        }
+    }
+}
+
+/// If `compose` is set, chain `f1` and `f2` with the closure of another pipeline, typically the
+/// `as_fn` of a second derived struct, so two structs' step sequences run as one.
+pub fn compose(cx: &Context) -> TokenStream {
+    if !cx.attrs.compose || !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        struct_params,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let ref_receiver = cx.ref_receiver();
+    let (start, from_seed) = from_seed(cx);
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let body = cx.chain_from(&from_seed, inner_item, trace_owned, &steps);
+    let other = Ident::new("other", struct_name.span());
+    let other_type = cx.method_param("O");
+    quote_spanned! { struct_name.span()=>
+        /// Returns a closure running `f1` then `f2` on the value it is called with and feeding
+        /// a success into `other`, e.g. `a.compose(f1, f2, b.as_fn(g1, g2))` to run the steps
+        /// of two pipelines in sequence.
+        #skip_doc
+        pub fn compose<#(#step_types,)* #other_type>(#ref_receiver, #(#steps: #step_types,)* #other: #other_type) -> impl Fn(#inner_type) -> #output + use<#(#struct_params,)* #(#step_types,)* #other_type>
+        where
+            #(#step_types: Fn(#inner_type) -> #output,)*
+            #other_type: Fn(#inner_type) -> #output,
+        {
+            #timeout_code
+            move |#start: #inner_type| -> #output {
+                let run = || -> #output { #body };
+                run().and_then(&#other)
+            }
+        }
+    }
+}
+
+/// `process3_batch` runs the same two steps over many caller-supplied seeds, built like `as_fn`
+/// but collecting the results right away.
+pub fn process3_batch(cx: &Context) -> TokenStream {
+    if !cx.steps_allowed(2) {
+        return quote! {};
+    }
+    let Context {
+        struct_name,
+        inner_type,
+        output,
+        inner_item,
+        trace_owned,
+        skip_doc,
+        timeout_code,
+        ..
+    } = cx;
+    let ref_receiver = cx.ref_receiver();
+    let (start, from_seed) = from_seed(cx);
+    let (steps, step_types) = step_params(2, struct_name.span());
+    let body = cx.chain_from(&from_seed, inner_item, trace_owned, &steps);
+    let seeds = Ident::new("seeds", struct_name.span());
+    let iter = cx.method_param("I");
+    quote_spanned! { struct_name.span()=>
+        /// Runs `f1` then `f2` on every value of `seeds`, ignoring the stored value, and
+        /// returns the results in order.
+        #skip_doc
+        pub fn process3_batch<#iter, #(#step_types),*>(#ref_receiver, #seeds: #iter, #(#steps: #step_types),*) -> Vec<#output>
+        where
+            #iter: IntoIterator<Item = #inner_type>,
+            #(#step_types: Fn(#inner_type) -> #output,)*
+        {
+            #timeout_code
+            #seeds
+                .into_iter()
+                .map(|#start: #inner_type| -> #output { #body })
+                .collect()
+        }
+    }
+}
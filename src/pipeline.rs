@@ -1,160 +1,544 @@
 use crate::attributes::PipelineAttributes;
+use crate::context::Context;
 use crate::errors::{Error, Result};
-use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned};
-use syn::{
-    DeriveInput, GenericArgument, Type, TypePath, WherePredicate, parse_quote, spanned::Spanned,
-};
+use crate::methods::{adapters, arity, dynamic, fields, items, lazy, recorded, reported, seeded};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{ToTokens, quote, quote_spanned};
+use syn::{DeriveInput, GenericArgument, Ident, Type, TypePath, spanned::Spanned};
 
-/// Generates the pipeline methods for a struct with a single field of type `Option<T>`.
+/// Wrapper type of the pipeline field, deciding how the chained steps short-circuit.
+///
+/// Both wrappers expose `and_then`, so the generated chains are identical apart from the
+/// step signatures.
+pub enum FieldKind<'a> {
+    /// `Option<T>`: steps return `Option<T>` and the chain stops at the first `None`.
+    Option,
+    /// `Result<T, E>`: steps return `Result<T, E>` and the chain stops at the first `Err`.
+    Result(&'a Type),
+}
+
+impl FieldKind<'_> {
+    /// Wraps `inner` in the field's wrapper type, e.g. `Option<T>` or `Result<T, E>`.
+    pub fn wrap(&self, inner: impl ToTokens) -> TokenStream {
+        match self {
+            FieldKind::Option => quote! { ::core::option::Option<#inner> },
+            FieldKind::Result(err_type) => quote! { ::core::result::Result<#inner, #err_type> },
+        }
+    }
+
+    /// Unwraps the successful value of `expr`, otherwise runs `on_fail` and returns the
+    /// `None`/`Err` from the enclosing method.
+    pub fn unwrap_or_return(&self, expr: impl ToTokens, on_fail: impl ToTokens) -> TokenStream {
+        match self {
+            FieldKind::Option => quote! {
+                match #expr {
+                    Some(value) => value,
+                    None => {
+                        #on_fail
+                        return None;
+                    }
+                }
+            },
+            FieldKind::Result(_) => quote! {
+                match #expr {
+                    Ok(value) => value,
+                    Err(err) => {
+                        #on_fail
+                        return Err(err);
+                    }
+                }
+            },
+        }
+    }
+
+    /// Unwraps the successful value of `expr`, otherwise runs `on_fail`, which must diverge.
+    pub fn unwrap_or_else(&self, expr: impl ToTokens, on_fail: impl ToTokens) -> TokenStream {
+        match self {
+            FieldKind::Option => quote! {
+                match #expr {
+                    Some(value) => value,
+                    None => {
+                        #on_fail
+                    }
+                }
+            },
+            FieldKind::Result(_) => quote! {
+                match #expr {
+                    Ok(value) => value,
+                    Err(_) => {
+                        #on_fail
+                    }
+                }
+            },
+        }
+    }
+
+    /// Pattern matching a failed value, binding the error of a `Result` as `err`.
+    pub fn failure_pattern(&self) -> TokenStream {
+        match self {
+            FieldKind::Option => quote! { None },
+            FieldKind::Result(_) => quote! { Err(err) },
+        }
+    }
+
+    /// Rebuilds the failed value bound by `failure_pattern`. Only the `last` use moves the
+    /// error; earlier ones clone it.
+    pub fn failure(&self, last: bool) -> TokenStream {
+        match self {
+            FieldKind::Option => quote! { None },
+            FieldKind::Result(_) if last => quote! { Err(err) },
+            FieldKind::Result(_) => quote! { Err(Clone::clone(&err)) },
+        }
+    }
+
+    /// Constructor for a successful value of the wrapper, i.e. `Some` or `Ok`.
+    pub fn success(&self) -> TokenStream {
+        match self {
+            FieldKind::Option => quote! { Some },
+            FieldKind::Result(_) => quote! { Ok },
+        }
+    }
+}
+
+/// Generates the pipeline methods for a struct with a single field of type `Option<T>` or
+/// `Result<T, E>`.
 ///
 /// This function supports generic structs by forwarding generics and where clauses,
-/// ensuring the inner type `T` is bound by `Clone`.
+/// ensuring the inner type `T` (and `E` for `Result` fields) is bound by `Clone`.
+///
+/// The struct and its attributes are validated by `Context::new`; each generated method comes from
+/// its own function in the `methods` module.
 ///
 /// Recognized attributes:
-/// - `skip = true`: disables pipeline processing, generating stub methods returning `None`.
+/// - `skip = true`: disables pipeline processing, generating stub methods returning `None`
+///   (`Option` fields only). `skip = false` is the same as omitting it. `1` and `0` are
+///   accepted for `true` and `false`.
+/// - `skip_if = path`: calls the `fn() -> bool` at `path` when a run starts and treats the field
+///   as `None` for that run if it returns true (`Option` fields only).
+/// - `force_skip_cfg`: skips every run when the deriving crate is built with
+///   `--cfg pipeline_force_skip`, as if a `skip_if` predicate returned true (`Option` fields
+///   only).
 /// - `timeout = u64`: if set, injects a print statement to log pipeline timeout on method calls.
+/// - `timeout_field = "name"`: reads the timeout from the struct field `name` instead, which is
+///   the one field allowed besides the pipeline field.
+/// - `timeout_action = "none" | "panic" | "log"`: enforces `timeout`, checking the deadline
+///   before every step after the first. On expiry the run stops with `None` (`Option` fields
+///   only), panics, or logs to stderr and continues. `Option` fields default to `none`; `Result`
+///   fields have no default and are only timed with an explicit action.
+/// - `export_trait = "Name"`: also generates a trait `Name` with the pipeline methods as default
+///   impls, implemented for the struct (`Option` fields only).
+/// - `error_enum = "Name"`: generates an error enum `Name` and a `try_process3` method returning
+///   `Result<T, Name>`, reporting the step at which a run stopped (`Option` fields only).
+/// - `serde`: generates `to_inner`/`from_inner` helpers (requires the `serde` feature).
+/// - `par_iter`: for `Option<Vec<T>>` fields, generates `process3_par_iter`, running the steps
+///   over the elements in parallel with rayon (requires the `rayon` feature).
+/// - `generator`: generates `process3_gen`, an iterator yielding the value after each successful
+///   step, built on nightly `gen` blocks (requires the `nightly` feature).
+/// - `chain_type = "try"`: chains the steps like the `?` operator, stopping at the first `None`
+///   or `Err`. It is the default and only chain type, shared by `Option` and `Result` fields.
+/// - `mode = "iterator"`: generates `process3_iter`, returning a generated `{Struct}Steps`
+///   iterator that runs one step per `next` call.
+/// - `mode = "stepper"`: generates `process3_stepper`, returning a generated `{Struct}Stepper`
+///   whose `step` runs one step per call and returns a `{Struct}State`, `Pending` or `Done`.
+/// - `config`: generates a `{Struct}Config` builder holding an optional seed and boxed steps, and
+///   a `run` method executing it.
+/// - `panic_on_none` or `panic_on_none = "message"`: the core methods return `T`, panicking with
+///   the message where they would return `None`.
+/// - `from_option`: implements `From<Option<T>>` (or `From<Result<T, E>>`) for the struct, so a
+///   pipeline can be built with `.into()`.
+/// - `first_some`: also generates `processN_first_some` methods, which try each step on a clone
+///   of the seed and return the first success.
+/// - `mutate_field`: generated methods take `&mut self` and write each successful step's value
+///   back into the field, so it holds the last good value if a later step fails or panics.
+/// - `min_steps = usize`: methods taking fewer steps are not generated.
+/// - `max_arity = usize`: generates `process3` up to `process{max_arity}` (at most 16) instead of
+///   just `process3`/`process4`, along with the matching `into_*`, `*_from` and trait methods.
+/// - `both_receivers`: also generates consuming `into_process3`/`into_process4` methods that
+///   move the value out instead of cloning it, usable without `T: Clone`.
+/// - `take`: also generates `process3_take(&mut self, ..)`, which moves the value out of the
+///   field, leaving `None`, instead of cloning it (`Option` fields only).
+/// - `inner = "Type"`: uses `Type` as the inner type instead of extracting it from the field, for
+///   field types the macro cannot introspect (aliases, macro-generated types). The field must
+///   still be an `Option<Type>`. It takes precedence over the extracted type.
+/// - `clone_bound = "predicates"`: replaces the `Clone` predicates added to the bounded impls,
+///   e.g. for inner types only known to be `Clone` through another bound.
+/// - `bound = "predicates"`: adds where predicates to the impls holding the generated methods,
+///   alongside the `Clone` ones, e.g. for bounds the steps rely on.
+/// - `copy`: bounds the inner (and error) type by `Copy` instead of `Clone` and copies the seed
+///   out of the field instead of cloning it.
+/// - `eq_check`: the core methods stop with the current value as soon as a step returns a value
+///   equal to its input, skipping the remaining steps (requires `T: PartialEq`).
+/// - `on_none = path`: calls `path(step_index)` once when a run short-circuits on `None`; a
+///   `path()` taking no arguments works too.
+/// - `stop_on_err_log`: prints the error of the failing step to stderr before propagating it
+///   (`Result` fields only, requires `E: Debug`).
+/// - `trace_to = path`: calls `path(&value)` with the value produced by every successful step.
+/// - `step_names = ["a", "b", ...]`: names the step parameters `f1, f2, ...`; the timeout and log
+///   messages then report the failing step's name instead of its index, while `on_none` still
+///   receives the index and `steps()` lists the names. One name is needed per step of the
+///   longest generated method.
+/// - `version = 1 | 2`: selects the naming scheme of the core methods. Version 1 (the default)
+///   generates `process3`/`process4`; version 2 replaces them with `process(steps)`, taking any
+///   iterable of steps.
+/// - `accessors`: generates `clear`/`set` helpers resetting the field to `None` or `Some(value)`
+///   (`Option` fields only).
+/// - `peek`: generates `peek`, borrowing the stored value without cloning it or running any steps.
+/// - `into_parts`: generates `into_parts`, consuming the struct and moving the stored value out.
+/// - `run_dyn`: generates `run_dyn`, running an owned `Vec` of boxed `FnMut` steps.
+/// - `as_fn`: generates `as_fn`, returning the two-step pipeline as a reusable closure.
+/// - `with_step`: generates `with_step`, binding the first of two steps and returning a closure
+///   awaiting the second.
+/// - `process_mixed`: generates `process_mixed`, running a fallible step and then an infallible
+///   one.
+/// - `field_name`: generates a `FIELD_NAME` associated constant naming the pipeline field.
+/// - `compose`: generates `compose`, chaining two steps with another pipeline's closure.
+/// - `apply_all`: generates `apply_all`, applying one step a run-time number of times.
+/// - `ok_or`: generates `process3_ok_or`, returning a caller-supplied error instead of `None`
+///   (`Option` fields only).
+/// - `assert_send`: asserts at compile time that the struct is `Send`, so a non-`Send` field is
+///   reported at the derive rather than where the struct is first sent across threads.
+/// - `feature_gate = "name"`: compiles every generated item only with the deriving crate's
+///   Cargo feature `name` enabled.
+/// - `isolate`: emits the generated impls inside an anonymous `const _: () = { .. };` block.
+/// - `doc_hidden`: marks the generated inherent impl blocks `#[doc(hidden)]`.
+/// - `namespace`: declares a `{Struct}Pipeline` marker type and generates the methods as its
+///   associated functions, taking the struct as the first argument, e.g.
+///   `PointPipeline::process3(&point, f1, f2)`.
+/// - `inline_always`: marks the generated `process`/`processN` methods `#[inline(always)]`.
+/// - `derive_debug`: implements `Debug` showing the struct and whether its field holds a value,
+///   without requiring `T: Debug`.
+/// - `bench`: passes every step's value through `std::hint::black_box`, keeping the optimizer
+///   from eliding steps in benchmarks.
+/// - `cow`: steps take and return `Cow<T>` borrowed from the field, cloning only when a step
+///   produces an owned value.
+///
+/// A field of type `Arc<Mutex<Option<T>>>` is also accepted; it is accessed through its lock.
+///
+/// Generated traits and types are declared with the struct's visibility.
 ///
 /// # Errors
 /// Returns an error if:
-/// - The struct does not have exactly one named field.
-/// - The single field is not of type `Option<T>` with a concrete generic argument.
+/// - The struct does not have exactly one named field besides the `timeout_field`.
+/// - The single field is not of type `Option<T>` or `Result<T, E>` with concrete generic arguments.
+/// - An `Option`-only attribute is combined with a `Result` field.
+/// - The inner type is the struct itself (recursive types must be boxed, e.g. `Option<Box<Self>>`).
 /// - The type path in the field's type is malformed.
 pub fn pipeline_derive(input: DeriveInput, attrs: &PipelineAttributes) -> Result<TokenStream> {
-    let struct_name = &input.ident;
-
-    // Validate that the struct has exactly one named field
-    let field = if let syn::Data::Struct(syn::DataStruct {
-        fields: syn::Fields::Named(fields),
+    let cx = Context::new(&input, attrs)?;
+    let Context {
+        struct_name,
+        hidden,
         ..
-    }) = &input.data
-    {
-        if fields.named.len() == 1 {
-            fields.named.first().unwrap()
-        } else {
-            return Err(Error::spanned(
-                &input.ident,
-                "Expected a struct with exactly one named field",
-            ));
+    } = &cx;
+    let (impl_generics, ty_generics, where_clause) = cx.generics.split_for_impl();
+    // With `namespace`, the methods go to the `{Struct}Pipeline` marker instead of the struct
+    let target = cx.target();
+
+    let process_method = dynamic::process(&cx);
+    let run_dyn = dynamic::run_dyn(&cx);
+    let apply_all = dynamic::apply_all(&cx);
+    let (run_config, config_type) = dynamic::run_config(&cx);
+    let fixed_arity_methods = arity::fixed_arity_methods(&cx);
+    let first_some_methods = arity::first_some_methods(&cx);
+    let process3_rev = adapters::process3_rev(&cx);
+    let process3_collect = recorded::process3_collect(&cx);
+    let process3_tuple = recorded::process3_tuple(&cx);
+    let process3_trace = recorded::process3_trace(&cx);
+    let process3_with_count = recorded::process3_with_count(&cx);
+    let process3_history = recorded::process3_history(&cx);
+    let process3_map = adapters::process3_map(&cx);
+    let process3_chain = adapters::process3_chain(&cx);
+    let process3_map_err = adapters::process3_map_err(&cx);
+    let process3_total_time = adapters::process3_total_time(&cx);
+    let process3_diag = reported::process3_diag(&cx);
+    let process3_to_writer = reported::process3_to_writer(&cx);
+    let process3_into = adapters::process3_into(&cx);
+    let process_mixed = adapters::process_mixed(&cx);
+    let process3_within = adapters::process3_within(&cx);
+    let process3_ctx = adapters::process3_ctx(&cx);
+    let with_step = adapters::with_step(&cx);
+    let process3_ok_or = adapters::process3_ok_or(&cx);
+    let process3_all_errors = adapters::process3_all_errors(&cx);
+    let try_process3 = adapters::try_process3(&cx);
+    let process3_gen = lazy::process3_gen(&cx);
+    let (process3_iter, steps_iterator) = lazy::process3_iter(&cx);
+    let (process3_stepper, stepper_items) = lazy::process3_stepper(&cx);
+
+    // Generate the pipeline methods with chained processing steps. Their signatures wrap the
+    // user's types in several layers, e.g. `FnOnce(fn(i32) -> i32) -> Option<fn(i32) -> i32>`,
+    // which a function pointer or sizeable `T` pushes over clippy's `type_complexity` threshold
+    // in the deriving crate. With `max_arity`, the longest methods also exceed its
+    // `too_many_arguments` limit. A private struct calling only some of the methods would get
+    // `dead_code` warnings for the rest, so every generated inherent impl allows those as well.
+    let methods = quote_spanned! { struct_name.span()=>
+        #[allow(dead_code, clippy::type_complexity, clippy::too_many_arguments)]
+        #hidden
+        impl #impl_generics #target #ty_generics #where_clause {
+            #process_method
+            #run_dyn
+            #apply_all
+            #run_config
+            #fixed_arity_methods
+            #first_some_methods
+            #process3_rev
+            #process3_collect
+            #process3_tuple
+            #process3_with_count
+            #process3_history
+            #process3_trace
+            #process3_map
+            #process3_chain
+            #process3_map_err
+            #process3_total_time
+            #process3_diag
+            #process3_to_writer
+            #process3_into
+            #process_mixed
+            #process3_within
+            #process3_ctx
+            #with_step
+            #process3_ok_or
+            #process3_all_errors
+            #try_process3
+            #process3_gen
+            #process3_iter
+            #process3_stepper
+        }
+    };
+
+    // Methods that never clone the stored value live in their own impl without the injected
+    // `Clone` bounds, so they stay usable when the inner type is not `Clone`
+    let (unbounded_impl_generics, unbounded_ty_generics, _) = input.generics.split_for_impl();
+    let mut logged_generics = input.generics.clone();
+    if !cx.log_bounds.is_empty() || !attrs.bound.is_empty() {
+        logged_generics
+            .make_where_clause()
+            .predicates
+            .extend(cx.log_bounds.iter().chain(&attrs.bound).cloned());
+    }
+    let logged_where_clause = &logged_generics.where_clause;
+    let consuming_methods = seeded::consuming_methods(&cx);
+    let take_method = seeded::take_method(&cx);
+    let from_methods = seeded::from_methods(&cx);
+    let as_fn = seeded::as_fn(&cx);
+    let compose = seeded::compose(&cx);
+    let process3_batch = seeded::process3_batch(&cx);
+    let peek = fields::peek(&cx);
+    let into_parts = fields::into_parts(&cx);
+    let accessors = fields::accessors(&cx);
+    let steps_fn = fields::steps_fn(&cx);
+    let field_name_const = fields::field_name_const(&cx);
+
+    let unbounded_methods = quote_spanned! { struct_name.span()=>
+        #[allow(dead_code, clippy::type_complexity, clippy::too_many_arguments)]
+        #hidden
+        impl #unbounded_impl_generics #target #unbounded_ty_generics #logged_where_clause {
+            #peek
+            #into_parts
+            #consuming_methods
+            #take_method
+            #from_methods
+            #as_fn
+            #compose
+            #process3_batch
+            #accessors
+            #steps_fn
+            #field_name_const
+        }
+    };
+
+    let marker_type = items::marker_type(&cx);
+    let exported_trait = items::exported_trait(&cx);
+    let error_enum = items::error_enum(&cx);
+    let serde_helpers = items::serde_helpers(&cx);
+    let from_impl = items::from_impl(&cx);
+    let par_iter_method = items::par_iter_method(&cx);
+    let send_assertion = items::send_assertion(&cx);
+    let debug_impl = items::debug_impl(&cx);
+    let on_none_items = items::on_none_items(&cx);
+
+    let output = quote! {
+        #marker_type
+        #methods
+        #unbounded_methods
+        #exported_trait
+        #error_enum
+        #steps_iterator
+        #stepper_items
+        #config_type
+        #serde_helpers
+        #from_impl
+        #par_iter_method
+        #send_assertion
+        #debug_impl
+        #on_none_items
+    };
+
+    // With `isolate`, the impls move into an anonymous const block. Impls apply crate-wide
+    // wherever they are written, so the methods keep working; the types, enums and traits users
+    // name stay outside, at the struct's path.
+    let output = if attrs.isolate {
+        let (impls, named): (Vec<syn::Item>, Vec<syn::Item>) = syn::parse2::<syn::File>(output)?
+            .items
+            .into_iter()
+            .partition(|item| matches!(item, syn::Item::Impl(_) | syn::Item::Const(_)));
+        quote! {
+            #(#named)*
+            const _: () = {
+                #(#impls)*
+            };
         }
     } else {
-        return Err(Error::spanned(
-            &input.ident,
-            "Expected a struct with named fields",
-        ));
+        output
     };
 
-    // Extract the identifier of the single named field
-    let field_ident = field
-        .ident
-        .as_ref()
-        .ok_or_else(|| Error::spanned(field, "Expected named field with identifier"))?;
+    // With `feature_gate`, every generated item is compiled only when the feature is enabled
+    match &attrs.feature_gate {
+        Some(feature) => {
+            let items = syn::parse2::<syn::File>(output)?.items;
+            Ok(quote! { #(#[cfg(feature = #feature)] #items)* })
+        }
+        None => Ok(output),
+    }
+}
 
-    // Extract inner type T from a field with type Option<T>
-    let inner_type = if let Type::Path(TypePath { path, .. }) = &field.ty {
+/// Extracts the inner type `T` and the wrapper kind from a field of type `Option<T>` or
+/// `Result<T, E>`.
+///
+/// Only the last path segment is inspected, so qualified spellings such as
+/// `std::option::Option<T>` are accepted too, as is the turbofish form `Option::<T>`, which
+/// parses to the same angle-bracketed arguments.
+pub fn extract_inner_type(ty: &Type) -> Result<(&Type, FieldKind<'_>)> {
+    let ty = ungroup(ty);
+    if let Type::Path(TypePath { path, .. }) = ty {
         let last_segment = path
             .segments
             .last()
-            .ok_or_else(|| Error::spanned(&field.ty, "Malformed type path in field type"))?;
-        if last_segment.ident != "Option" {
+            .ok_or_else(|| Error::spanned(ty, "Malformed type path in field type"))?;
+        if last_segment.ident != "Option" && last_segment.ident != "Result" {
             return Err(Error::spanned(
                 last_segment,
-                "Expected field of type Option<T>",
+                "Expected field of type Option<T> or Result<T, E>",
+            )
+            .span_help(
+                last_segment.span(),
+                "for an alias of Option<T>, declare the inner type with #[pipeline(inner = \"T\")]",
             ));
         }
         if let syn::PathArguments::AngleBracketed(angle_bracketed) = &last_segment.arguments {
-            let Some(GenericArgument::Type(ty)) = angle_bracketed.args.first() else {
+            let Some(GenericArgument::Type(inner)) = angle_bracketed.args.first() else {
                 return Err(Error::spanned(
                     angle_bracketed,
-                    "Expected Option<T> with concrete type",
+                    format!("Expected {}<T> with concrete type", last_segment.ident),
                 ));
             };
-            ty
+            let kind = if last_segment.ident == "Result" {
+                let Some(GenericArgument::Type(err_type)) = angle_bracketed.args.iter().nth(1)
+                else {
+                    return Err(Error::spanned(
+                        angle_bracketed,
+                        "Expected Result<T, E> with concrete error type",
+                    ));
+                };
+                FieldKind::Result(err_type)
+            } else {
+                FieldKind::Option
+            };
+            Ok((inner, kind))
         } else {
-            return Err(Error::spanned(
+            Err(Error::spanned(
                 last_segment,
                 "Expected angle-bracketed generic arguments",
-            ));
+            ))
         }
     } else {
-        return Err(Error::spanned(
-            &field.ty,
-            "Expected field of type Option<T>",
-        ));
-    };
-
-    // Clone generics and add a `T: Clone` where bound to the generics for use in method definitions
-    let mut generics = input.generics.clone();
-    let clone_bound: WherePredicate = parse_quote! {
-        #inner_type: Clone
-    };
-    if let Some(ref mut wc) = generics.where_clause {
-        wc.predicates.push(clone_bound);
-    } else {
-        generics.where_clause = Some(syn::WhereClause {
-            where_token: Default::default(),
-            predicates: vec![clone_bound].into_iter().collect(),
-        });
+        Err(Error::spanned(
+            ty,
+            "Expected field of type Option<T> or Result<T, E>",
+        ))
     }
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-
-    // If `skip` attribute is true, generate dummy `process` methods that immediately return None
-    if attrs.skip {
-        return Ok(quote_spanned! { struct_name.span()=>
-            impl #impl_generics #struct_name #ty_generics #where_clause {
-                /// Always returns None because skip attribute is set.
-                pub fn process3<F, G>(&self, _f1: F, _f2: G) -> Option<#inner_type>
-                where
-                    F: FnOnce(#inner_type) -> Option<#inner_type>,
-                    G: FnOnce(#inner_type) -> Option<#inner_type>,
-                {
-                    None
-                }
-                /// Always returns None because skip attribute is set.
-                pub fn process4<F, G, H>(&self, _f1: F, _f2: G, _f3: H) -> Option<#inner_type>
-                where
-                    F: FnOnce(#inner_type) -> Option<#inner_type>,
-                    G: FnOnce(#inner_type) -> Option<#inner_type>,
-                    H: FnOnce(#inner_type) -> Option<#inner_type>,
-                {
-                    None
-                }
-            }
-        });
+}
+
+/// Looks through the invisible groups a `macro_rules!` expansion wraps around types substituted
+/// for `$ty` fragments, which would otherwise hide the path they contain.
+pub fn ungroup(ty: &Type) -> &Type {
+    match ty {
+        Type::Group(group) => ungroup(&group.elem),
+        _ => ty,
     }
+}
 
-    // If `timeout` attribute is set, generate code to print the timeout message on pipeline method calls
-    let timeout_code = if let Some(timeout) = attrs.timeout {
-        quote! {
-            println!("Pipeline timeout set to {} ms", #timeout);
-        }
-    } else {
-        quote! {}
-    };
+/// Builds the parameter names `f1, f2, ...` and type parameters `F1, F2, ...` for `steps` closure
+/// steps.
+///
+/// `span` must match the span the signature is quoted with, so the body can refer to the
+/// parameters even when the struct comes from a `macro_rules!` expansion.
+pub fn step_params(steps: usize, span: Span) -> (Vec<Ident>, Vec<Ident>) {
+    (1..=steps)
+        .map(|step| {
+            (
+                Ident::new(&format!("f{}", step), span),
+                Ident::new(&format!("F{}", step), span),
+            )
+        })
+        .unzip()
+}
 
-    // Generate the pipeline methods with chained processing steps using Option::and_then
-    Ok(quote_spanned! { struct_name.span()=>
-        impl #impl_generics #struct_name #ty_generics #where_clause {
-            /// Processes the inner Option<T> with two chained closure steps.
-            pub fn process3<F, G>(&self, f1: F, f2: G) -> Option<#inner_type>
-            where
-                F: FnOnce(#inner_type) -> Option<#inner_type>,
-                G: FnOnce(#inner_type) -> Option<#inner_type>,
-            {
-                #timeout_code
-                self.#field_ident.as_ref().cloned().and_then(f1).and_then(f2)
-            }
+/// Returns the 1-based position `n` of a step parameter `fn` built by `step_params`.
+pub fn step_number(step: &Ident) -> usize {
+    step.to_string()
+        .trim_start_matches('f')
+        .parse()
+        .expect("step parameters are named f1, f2, ...")
+}
 
-            /// Processes the inner Option<T> with three chained closure steps.
-            pub fn process4<F, G, H>(&self, f1: F, f2: G, f3: H) -> Option<#inner_type>
-            where
-                F: FnOnce(#inner_type) -> Option<#inner_type>,
-                G: FnOnce(#inner_type) -> Option<#inner_type>,
-                H: FnOnce(#inner_type) -> Option<#inner_type>,
-            {
-                #timeout_code
-                self.#field_ident.as_ref().cloned().and_then(f1).and_then(f2).and_then(f3)
+/// Spells out a step count for the generated doc comments, e.g. "two" for 2.
+pub fn count_word(steps: usize) -> String {
+    const WORDS: [&str; 16] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen",
+    ];
+    WORDS
+        .get(steps)
+        .map_or_else(|| steps.to_string(), |word| word.to_string())
+}
+
+/// Returns true if the tokens contain the `Self` type, at any depth.
+pub fn mentions_self(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Ident(ident) => ident == "Self",
+        TokenTree::Group(group) => mentions_self(group.stream()),
+        _ => false,
+    })
+}
+
+/// Returns the value type `X` of a shared `Arc<Mutex<X>>` field type, looking only at the last
+/// path segments.
+pub fn shared_value_type(ty: &Type) -> Option<&Type> {
+    single_type_argument(single_type_argument(ty, "Arc")?, "Mutex")
+}
+
+/// Returns the element type `T` of a `Vec<T>` type, looking only at the last path segment.
+pub fn vec_element_type(ty: &Type) -> Option<&Type> {
+    single_type_argument(ty, "Vec")
+}
+
+/// Returns `T` if `ty` is a path ending in `name<T>` with a single type argument.
+fn single_type_argument<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(TypePath { path, .. }) = ungroup(ty) else {
+        return None;
+    };
+    let last_segment = path.segments.last()?;
+    if last_segment.ident != name {
+        return None;
+    }
+    match &last_segment.arguments {
+        syn::PathArguments::AngleBracketed(angle_bracketed) if angle_bracketed.args.len() == 1 => {
+            match angle_bracketed.args.first()? {
+                GenericArgument::Type(argument) => Some(argument),
+                _ => None,
             }
         }
-    })
+        _ => None,
+    }
 }
@@ -0,0 +1,627 @@
+//! Parsing of the `#[pipeline(...)]` attribute and the options that only change how the
+//! generated code is emitted.
+
+use pipeline_derive::Pipeline;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Pipeline)]
+#[pipeline]
+struct Bare<T> {
+    value: Option<T>,
+}
+
+#[test]
+fn bare_attribute() {
+    let p = Bare { value: Some(1) };
+    assert_eq!(p.process3(|v| Some(v + 1), |v| Some(v * 2)), Some(4));
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 1000)]
+#[pipeline]
+#[pipeline(min_steps = 3)]
+struct Merged {
+    value: Option<i32>,
+}
+
+#[test]
+fn merged_attributes() {
+    let p = Merged { value: Some(1) };
+    assert_eq!(p.process4(Some, |v| Some(v + 1), Some), Some(2));
+}
+
+#[derive(Pipeline)]
+#[pipeline_derive::pipeline(skip)]
+struct Qualified {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[::pipeline_derive::pipeline(namespace)]
+#[pipeline(max_arity = 3)]
+struct QualifiedMerged {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline_derive::pipeline(namespace)]
+#[pipeline_derive::pipeline(skip)]
+struct QualifiedTwice {
+    value: Option<i32>,
+}
+
+extern crate pipeline_derive as renamed;
+
+mod reexport {
+    pub use pipeline_derive as pd;
+}
+
+#[derive(Pipeline)]
+#[renamed::pipeline(crate = "renamed", skip)]
+struct RenamedCrate {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[reexport::pd::pipeline(namespace)]
+#[reexport::pd::pipeline(crate = "reexport::pd", max_arity = 3)]
+struct ReexportedCrate {
+    value: Option<i32>,
+}
+
+#[test]
+fn qualified_attribute() {
+    assert_eq!(Qualified { value: Some(1) }.process3(Some, Some), None);
+    let p = QualifiedMerged { value: Some(1) };
+    assert_eq!(
+        QualifiedMergedPipeline::process3(&p, |v| Some(v + 1), Some),
+        Some(2)
+    );
+    let p = QualifiedTwice { value: Some(1) };
+    assert_eq!(QualifiedTwicePipeline::process3(&p, Some, Some), None);
+    assert_eq!(RenamedCrate { value: Some(1) }.process3(Some, Some), None);
+    let p = ReexportedCrate { value: Some(1) };
+    assert_eq!(ReexportedCratePipeline::process3(&p, Some, Some), Some(1));
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip)]
+struct SkipBare {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip = true)]
+struct SkipTrue {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip = false, timeout = 1000)]
+struct SkipFalse {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip = false)]
+struct SkipFalseResult {
+    value: Result<i32, ()>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip = 0)]
+struct SkipZero {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip = 1)]
+struct SkipOne {
+    value: Option<i32>,
+}
+
+macro_rules! skipped {
+    ($flag:literal) => {
+        #[derive(Pipeline)]
+        #[pipeline(skip = $flag)]
+        struct SkipMacro {
+            value: Option<i32>,
+        }
+    };
+}
+
+skipped!(1);
+
+#[test]
+fn skip_forms() {
+    let inc = |v: i32| Some(v + 1);
+    assert_eq!(SkipBare { value: Some(1) }.process3(inc, inc), None);
+    assert_eq!(SkipTrue { value: Some(1) }.process4(inc, inc, inc), None);
+    assert_eq!(SkipFalse { value: Some(1) }.process3(inc, inc), Some(3));
+    assert_eq!(SkipFalseResult { value: Ok(1) }.process3(Ok, Ok), Ok(1));
+    assert_eq!(SkipZero { value: Some(1) }.process3(inc, Some), Some(2));
+    assert_eq!(SkipOne { value: Some(1) }.process3(inc, Some), None);
+    assert_eq!(SkipMacro { value: Some(1) }.process3(inc, Some), None);
+}
+
+#[test]
+fn skip_ignores_steps() {
+    let p = SkipBare { value: Some(1) };
+    assert_eq!(
+        p.process3_rev(|_| panic!("step ran"), |_| panic!("step ran")),
+        None
+    );
+    assert_eq!(p.process3_from(1, Some, Some), None);
+}
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+mod flags {
+    pub fn disabled() -> bool {
+        super::DISABLED.load(super::Ordering::SeqCst)
+    }
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip_if = flags::disabled, both_receivers, take, export_trait = "SkipIf", as_fn)]
+struct Runtime {
+    value: Option<i32>,
+}
+
+#[test]
+fn skip_if() {
+    let ran = std::cell::Cell::new(0);
+    let p = Runtime { value: Some(1) };
+    let counted = |v: i32| {
+        ran.set(ran.get() + 1);
+        Some(v + 1)
+    };
+    assert_eq!(p.process3(counted, Some), Some(2));
+    DISABLED.store(true, Ordering::SeqCst);
+    assert_eq!(p.process3(counted, Some), None);
+    assert_eq!(p.process3_from(1, Some, Some), None);
+    assert_eq!(SkipIf::process3(&p, Some, Some), None);
+    assert_eq!(p.as_fn(Some, Some)(1), None);
+    let mut q = Runtime { value: Some(1) };
+    assert_eq!(q.process3_take(Some, Some), None);
+    assert_eq!(q.value, Some(1));
+    assert_eq!(Runtime { value: Some(1) }.into_process3(Some, Some), None);
+    DISABLED.store(false, Ordering::SeqCst);
+    assert_eq!(p.as_fn(Some, Some)(1), Some(1));
+    assert_eq!(ran.get(), 1);
+}
+
+#[derive(Pipeline)]
+#[pipeline(panic_on_none)]
+struct Unwrapped {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(panic_on_none = "config must load", mutate_field)]
+struct UnwrappedMessage {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(panic_on_none, version = 2)]
+struct UnwrappedV2 {
+    value: Option<i32>,
+}
+
+#[test]
+fn panic_on_none() {
+    let p = Unwrapped { value: Some(1) };
+    let v: i32 = p.process3(|v| Some(v + 1), Some);
+    assert_eq!(v, 2);
+    assert_eq!(p.process4(Some, Some, Some), 1);
+    // Only the main methods unwrap
+    assert_eq!(p.process3_map(Some, Some, |v| v), Some(1));
+    let mut m = UnwrappedMessage { value: Some(1) };
+    assert_eq!(m.process3(|v| Some(v * 5), Some), 5);
+    assert_eq!(m.value, Some(5));
+    let steps: Vec<fn(i32) -> Option<i32>> = vec![Some, |v| Some(v + 1)];
+    assert_eq!(UnwrappedV2 { value: Some(1) }.process(steps), 2);
+}
+
+#[test]
+#[should_panic(expected = "pipeline produced None")]
+fn panic_on_none_default_message() {
+    Unwrapped { value: Some(1) }.process3(|_| None, Some);
+}
+
+#[test]
+#[should_panic(expected = "config must load")]
+fn panic_on_none_custom_message() {
+    UnwrappedMessage { value: None }.process3(Some, Some);
+}
+
+#[derive(Pipeline)]
+#[pipeline(min_steps = 3, export_trait = "Min3")]
+struct MinSteps {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(max_arity = 16, both_receivers, export_trait = "Wide", min_steps = 14)]
+struct Wide16 {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(max_arity = 3)]
+struct Narrow {
+    value: Option<i32>,
+}
+
+#[test]
+fn arity_range() {
+    assert_eq!(
+        MinSteps { value: Some(1) }.process4(Some, Some, Some),
+        Some(1)
+    );
+    let p = Wide16 { value: Some(0) };
+    let s = |v: i32| Some(v + 1);
+    assert_eq!(
+        p.process16(s, s, s, s, s, s, s, s, s, s, s, s, s, s, s),
+        Some(15)
+    );
+    assert_eq!(
+        Wide::process15(&p, s, s, s, s, s, s, s, s, s, s, s, s, s, s),
+        Some(14)
+    );
+    assert_eq!(
+        p.process16_from(1, s, s, s, s, s, s, s, s, s, s, s, s, s, s, s),
+        Some(16)
+    );
+    let q = Wide16 { value: Some(0) };
+    assert_eq!(
+        q.into_process15(s, s, s, s, s, s, s, s, s, s, s, s, s, s),
+        Some(14)
+    );
+    assert_eq!(Narrow { value: Some(1) }.process3(s, s), Some(3));
+}
+
+#[derive(Pipeline)]
+#[pipeline(version = 2, mutate_field)]
+struct Version2 {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(version = 2, export_trait = "V2")]
+struct Version2Trait {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(version = 1)]
+struct Version1 {
+    value: Option<i32>,
+}
+
+#[test]
+fn version() {
+    let mut p = Version2 { value: Some(1) };
+    let steps: Vec<Box<dyn FnOnce(i32) -> Option<i32>>> = vec![
+        Box::new(|v| Some(v + 1)),
+        Box::new(|_| None),
+        Box::new(Some),
+    ];
+    assert_eq!(p.process(steps), None);
+    assert_eq!(p.value, Some(2));
+    assert_eq!(p.process([Some as fn(i32) -> Option<i32>; 4]), Some(2));
+    let t = Version2Trait { value: Some(2) };
+    assert_eq!(V2::process(&t, [Some as fn(i32) -> Option<i32>]), Some(2));
+    assert_eq!(p.process3_rev(Some, Some), Some(2));
+    assert_eq!(Version1 { value: Some(1) }.process3(Some, Some), Some(1));
+}
+
+type MaybeNum = Option<u32>;
+
+#[derive(Pipeline)]
+#[pipeline(inner = "u32")]
+struct InnerAlias {
+    value: MaybeNum,
+}
+
+type MaybeVec<T> = Option<Vec<T>>;
+
+#[derive(Pipeline)]
+#[pipeline(inner = "Vec<T>", both_receivers)]
+struct InnerGeneric<T> {
+    value: MaybeVec<T>,
+}
+
+#[test]
+fn inner() {
+    let p = InnerAlias { value: Some(2) };
+    assert_eq!(p.process3(|v| v.checked_sub(1), Some), Some(1));
+    let q = InnerGeneric {
+        value: Some(vec![1]),
+    };
+    let pushed = q.into_process3(Some, |mut v| {
+        v.push(2);
+        Some(v)
+    });
+    assert_eq!(pushed, Some(vec![1, 2]));
+}
+
+pub trait Source {
+    type Out: Clone + std::fmt::Debug;
+}
+
+struct Nums;
+
+impl Source for Nums {
+    type Out = i32;
+}
+
+type Slot<T> = Option<T>;
+
+#[derive(Pipeline)]
+#[pipeline(inner = "S::Out", clone_bound = "S::Out: Clone + std::fmt::Debug")]
+struct Projected<S: Source> {
+    value: Slot<S::Out>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(inner = "S::Out", clone_bound = "", export_trait = "Unbounded")]
+struct ProjectedImplied<S: Source> {
+    value: Slot<S::Out>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(clone_bound = "T: Copy, E: Copy")]
+struct CopyBound<T: Copy, E: Copy> {
+    value: Result<T, E>,
+}
+
+fn show<S: Source>(p: &Projected<S>) -> Option<String> {
+    p.process3(Some, Some).map(|v| format!("{v:?}"))
+}
+
+#[test]
+fn clone_bound() {
+    let p = Projected::<Nums> { value: Some(4) };
+    assert_eq!(p.process3(|v| Some(v + 1), Some), Some(5));
+    assert_eq!(show(&p).as_deref(), Some("4"));
+    let q = ProjectedImplied::<Nums> { value: Some(1) };
+    assert_eq!(q.process4(Some, Some, Some), Some(1));
+    assert_eq!(
+        Unbounded::process3(&ProjectedImplied::<Nums> { value: None }, Some, Some),
+        None
+    );
+    assert_eq!(CopyBound::<u8, ()> { value: Ok(1) }.process3(Ok, Ok), Ok(1));
+}
+
+#[derive(Pipeline)]
+#[pipeline(bound = "T: Send + 'static, T: std::fmt::Debug")]
+struct Bounded<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace, clone_bound = "T: Clone", bound = "T: Default")]
+struct BoundedNamespace<T> {
+    value: Result<T, String>,
+}
+
+#[test]
+fn bound() {
+    let p = Bounded {
+        value: Some(vec![1u8]),
+    };
+    let pushed = p.process3(Some, |mut v| {
+        v.push(2);
+        Some(v)
+    });
+    assert_eq!(pushed, Some(vec![1, 2]));
+    let q = BoundedNamespace {
+        value: Ok::<u8, String>(3),
+    };
+    assert_eq!(
+        BoundedNamespacePipeline::process3(&q, |v| Ok(v + u8::default()), Ok),
+        Ok(3)
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Pipeline)]
+#[pipeline(copy)]
+struct Copied {
+    value: Option<Point>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(copy, mutate_field, namespace)]
+struct CopiedResult<T: Copy> {
+    value: Result<T, u8>,
+}
+
+#[test]
+fn copy() {
+    let p = Copied {
+        value: Some(Point { x: 1, y: 2 }),
+    };
+    let moved = p.process3(
+        |p| Some(Point { x: p.x + 1, ..p }),
+        |p| Some(Point { y: p.y * 2, ..p }),
+    );
+    assert_eq!(moved, Some(Point { x: 2, y: 4 }));
+    let mut r = CopiedResult { value: Ok(3u32) };
+    assert_eq!(
+        CopiedResultPipeline::process3(&mut r, |v| Ok(v + 1), |v| Ok(v * 2)),
+        Ok(8)
+    );
+    assert_eq!(r.value, Ok(8));
+}
+
+#[derive(Pipeline)]
+#[pipeline(inline_always, max_arity = 5, mutate_field)]
+struct Inlined<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(bench, both_receivers)]
+struct Benched {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(doc_hidden, export_trait = "Hidden")]
+pub struct DocHidden {
+    value: Option<i32>,
+}
+
+#[test]
+fn emission_flags_keep_results() {
+    let mut p = Inlined { value: Some(1) };
+    assert_eq!(p.process5(Some, Some, Some, |v| Some(v + 1)), Some(2));
+    let b = Benched { value: Some(1) };
+    let inc = |v: i32| Some(v + 1);
+    assert_eq!(b.process4(inc, inc, inc), Some(4));
+    assert_eq!(b.process3_tuple(inc, |_| None), (Some(2), None));
+    assert_eq!(Benched { value: Some(1) }.into_process3(inc, inc), Some(3));
+    let h = DocHidden { value: Some(1) };
+    assert_eq!(h.process3(inc, inc), Some(3));
+    assert_eq!(Hidden::process3(&h, inc, inc), Some(3));
+}
+
+#[derive(Pipeline)]
+#[pipeline(assert_send)]
+struct SendRef<'a, T: Send + Sync> {
+    value: Option<&'a T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(assert_send = true)]
+struct SendArray<T: Send, const N: usize> {
+    value: Result<[T; N], String>,
+}
+
+#[test]
+fn assert_send() {
+    assert_eq!(SendRef { value: Some(&1) }.process3(Some, Some), Some(&1));
+    assert_eq!(
+        SendArray {
+            value: Ok([1u8; 2])
+        }
+        .process3(Ok, Ok),
+        Ok([1, 1])
+    );
+}
+
+mod isolated {
+    use super::Pipeline;
+
+    #[derive(Pipeline)]
+    #[pipeline(isolate, mode = "iterator", config, namespace)]
+    pub struct Isolated {
+        pub value: Option<i32>,
+    }
+}
+
+#[test]
+fn isolate() {
+    let p = isolated::Isolated { value: Some(1) };
+    assert_eq!(
+        isolated::IsolatedPipeline::run(&p, isolated::IsolatedConfig::new()),
+        Some(1)
+    );
+    let steps: isolated::IsolatedSteps<_, _> =
+        isolated::IsolatedPipeline::process3_iter(&p, Some, |v| Some(v + 1));
+    assert_eq!(steps.collect::<Vec<_>>(), [1, 2]);
+}
+
+// Gated on a feature of this package, which integration tests see like a deriving crate's own
+#[derive(Pipeline)]
+#[pipeline(
+    feature_gate = "serde",
+    export_trait = "Gated",
+    error_enum = "GatedError",
+    namespace
+)]
+pub struct FeatureGated {
+    value: Option<i32>,
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn feature_gate_on() {
+    let p = FeatureGated { value: Some(1) };
+    assert_eq!(FeatureGatedPipeline::process3(&p, Some, Some), Some(1));
+    assert_eq!(Gated::process3(&p, Some, Some), Some(1));
+    assert_eq!(
+        GatedError::Timeout { step: 1 }.to_string(),
+        "pipeline timed out before step 1"
+    );
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn feature_gate_off() {
+    // These would clash with the generated items if the gate let them through
+    #[allow(dead_code)]
+    struct FeatureGatedPipeline;
+    #[allow(dead_code)]
+    trait Gated {}
+    assert_eq!(FeatureGated { value: Some(1) }.value, Some(1));
+}
+
+macro_rules! literal_timeout {
+    ($ms:literal) => {
+        #[derive(Pipeline)]
+        #[pipeline(timeout = $ms)]
+        struct LiteralTimeout {
+            value: Option<i32>,
+        }
+    };
+}
+
+literal_timeout!(1000);
+
+macro_rules! expr_timeout {
+    ($ms:expr) => {
+        #[derive(Pipeline)]
+        #[pipeline(timeout = $ms)]
+        struct ExprTimeout {
+            value: Option<i32>,
+        }
+    };
+}
+
+expr_timeout!(1000);
+
+fn never() -> bool {
+    false
+}
+
+macro_rules! fragments {
+    ($name:literal, $predicate:path, $names:expr) => {
+        #[derive(Pipeline)]
+        #[pipeline(export_trait = $name, skip_if = $predicate, step_names = $names, skip = false)]
+        struct Fragments {
+            value: Option<i32>,
+        }
+    };
+}
+
+fragments!("Fragmented", never, ["a", "b", "c"]);
+
+#[test]
+fn macro_fragment_values() {
+    assert_eq!(
+        LiteralTimeout { value: Some(1) }.process3(Some, Some),
+        Some(1)
+    );
+    assert_eq!(ExprTimeout { value: Some(1) }.process3(Some, Some), Some(1));
+    let f = Fragments { value: Some(1) };
+    assert_eq!(Fragmented::process3(&f, |v| Some(v + 1), Some), Some(2));
+}
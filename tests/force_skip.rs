@@ -0,0 +1,42 @@
+//! Builds under `--cfg pipeline_force_skip`, which skips the pipelines with `force_skip_cfg`:
+//! `RUSTFLAGS="--cfg pipeline_force_skip" cargo test --test force_skip`.
+#![cfg(pipeline_force_skip)]
+
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+#[pipeline(skip = false, force_skip_cfg, take, export_trait = "Forced")]
+struct Plain {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip_if = never, force_skip_cfg)]
+struct Predicated {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+struct Unmarked {
+    value: Option<i32>,
+}
+
+fn never() -> bool {
+    false
+}
+
+#[test]
+fn marked_pipelines_are_skipped() {
+    let mut p = Plain { value: Some(1) };
+    assert_eq!(p.process3(|_| panic!("step ran"), Some), None);
+    assert_eq!(p.process3_from(1, Some, Some), None);
+    assert_eq!(Forced::process3(&p, Some, Some), None);
+    assert_eq!(p.process3_take(Some, Some), None);
+    assert_eq!(p.value, Some(1));
+    assert_eq!(Predicated { value: Some(1) }.process3(Some, Some), None);
+}
+
+#[test]
+fn unmarked_pipelines_still_run() {
+    assert_eq!(Unmarked { value: Some(1) }.process3(Some, Some), Some(1));
+}
@@ -0,0 +1,411 @@
+//! Items generated next to the struct: the exported trait, the error enum, the `config`
+//! builder, the iterator and stepper types, the `namespace` marker and the trait impls.
+
+use pipeline_derive::Pipeline;
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[derive(Pipeline)]
+#[pipeline(export_trait = "MyPipeline")]
+pub struct Exported<T> {
+    value: Option<T>,
+}
+
+struct Other(i32);
+
+impl MyPipeline for Other {
+    type Item = i32;
+
+    fn pipeline_seed(&self) -> Option<i32> {
+        Some(self.0)
+    }
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip, export_trait = "Skipped")]
+struct ExportedSkipped {
+    value: Option<i32>,
+}
+
+#[test]
+fn export_trait() {
+    let p = Exported { value: Some(1) };
+    assert_eq!(
+        MyPipeline::process3(&p, |v| Some(v + 1), |v| Some(v * 2)),
+        Some(4)
+    );
+    let other = Other(2);
+    assert_eq!(
+        other.process4(|v| Some(v + 1), |v| Some(v * 2), |v| Some(v - 1)),
+        Some(5)
+    );
+    assert_eq!(
+        Skipped::process3(&ExportedSkipped { value: Some(1) }, Some, Some),
+        None
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(error_enum = "PipelineError", timeout = 5, timeout_action = "none")]
+pub struct Errors {
+    value: Option<i32>,
+}
+
+mod inner {
+    #[derive(pipeline_derive::Pipeline)]
+    #[pipeline(error_enum = "CowError", cow)]
+    pub(crate) struct Scoped<T> {
+        pub(crate) value: Option<T>,
+    }
+}
+
+#[test]
+fn error_enum() {
+    let p = Errors { value: Some(1) };
+    assert_eq!(p.try_process3(|v| Some(v + 1), Some), Ok(2));
+    assert_eq!(
+        p.try_process3(Some, |_| None),
+        Err(PipelineError::EarlyStop { step: 2 })
+    );
+    let empty = Errors { value: None };
+    assert_eq!(
+        empty.try_process3(Some, Some),
+        Err(PipelineError::EarlyStop { step: 0 })
+    );
+    let slow = |v| {
+        sleep(Duration::from_millis(20));
+        Some(v)
+    };
+    let err = p.try_process3(slow, slow).unwrap_err();
+    assert_eq!(err, PipelineError::Timeout { step: 2 });
+    assert_eq!(err.to_string(), "pipeline timed out before step 2");
+    assert_eq!(
+        PipelineError::EarlyStop { step: 1 }.to_string(),
+        "pipeline stopped at step 1"
+    );
+    let nothing = PipelineError::EarlyStop { step: 0 };
+    assert_eq!(nothing.to_string(), "pipeline has no value to process");
+    let boxed: Box<dyn std::error::Error> = Box::new(err);
+    assert!(boxed.to_string().contains("timed out"));
+    let c = inner::Scoped { value: Some(3) };
+    assert_eq!(c.try_process3(Some, Some).map(|v| *v), Ok(3));
+    assert_eq!(
+        c.try_process3(|_| None, Some),
+        Err(inner::CowError::EarlyStop { step: 1 })
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(config)]
+pub struct Configured {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(config, mutate_field)]
+struct ConfiguredResult<F: Clone> {
+    value: Result<F, String>,
+}
+
+fn always() -> bool {
+    true
+}
+
+#[derive(Pipeline)]
+#[pipeline(config, skip_if = always)]
+struct ConfiguredSkipped {
+    value: Option<i32>,
+}
+
+struct Holder<'s> {
+    config: ConfiguredConfig<'s>,
+}
+
+#[test]
+fn config() {
+    let p = Configured { value: Some(2) };
+    let mut calls = 0;
+    let config = ConfiguredConfig::new().step(|v| Some(v + 1)).step(|v| {
+        calls += 1;
+        Some(v * 10)
+    });
+    assert_eq!(p.run(config), Some(30));
+    assert_eq!(calls, 1);
+    let seeded = ConfiguredConfig::default()
+        .seed(Some(5))
+        .step(|v| Some(v - 1));
+    assert_eq!(p.run(seeded), Some(4));
+    assert_eq!(p.run(ConfiguredConfig::new().seed(None)), None);
+    assert_eq!(
+        p.run(
+            Holder {
+                config: ConfiguredConfig::new()
+            }
+            .config
+        ),
+        Some(2)
+    );
+    let mut r = ConfiguredResult { value: Ok(1u8) };
+    let failing = ConfiguredResultConfig::new()
+        .step(|v| Ok(v + 1))
+        .step(|_| Err("no".into()));
+    assert_eq!(r.run(failing), Err("no".to_string()));
+    assert_eq!(r.value, Ok(2));
+    let s = ConfiguredSkipped { value: Some(1) };
+    assert_eq!(s.run(ConfiguredSkippedConfig::new().seed(Some(3))), None);
+}
+
+thread_local! {
+    static MISSES: Cell<usize> = const { Cell::new(0) };
+    static HOOK: Cell<usize> = const { Cell::new(99) };
+}
+
+fn miss(_: usize) {
+    MISSES.with(|m| m.set(m.get() + 1));
+}
+
+fn hook(step: usize) {
+    HOOK.with(|h| h.set(step));
+}
+
+#[derive(Pipeline)]
+#[pipeline(mode = "iterator", on_none = miss)]
+struct Iterated {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mode = "iterator")]
+struct IteratedResult<T: Clone = u8> {
+    value: Result<T, String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mode = "iterator", namespace, skip)]
+pub struct IteratedSkipped<'a> {
+    value: Option<&'a str>,
+}
+
+#[test]
+fn iterator_mode() {
+    let p = Iterated { value: Some(1) };
+    let mut it = p.process3_iter(|v| Some(v + 1), |v| Some(v * 10));
+    assert_eq!(it.next(), Some(2));
+    assert_eq!(it.next(), Some(20));
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next(), None);
+    let ran = Cell::new(false);
+    let late = |v| {
+        ran.set(true);
+        Some(v)
+    };
+    assert_eq!(p.process3_iter(|_| None, late).count(), 0);
+    assert!(!ran.get());
+    assert_eq!(MISSES.with(Cell::get), 1);
+    assert_eq!(
+        Iterated { value: None }.process3_iter(Some, Some).count(),
+        0
+    );
+    assert_eq!(MISSES.with(Cell::get), 2);
+    let r: IteratedResult = IteratedResult { value: Ok(1) };
+    let values: Vec<u8> = r
+        .process3_iter(|v| Ok(v + 1), |_| Err("e".into()))
+        .collect();
+    assert_eq!(values, [2]);
+    let s = IteratedSkipped { value: Some("a") };
+    assert_eq!(
+        IteratedSkippedPipeline::process3_iter(&s, Some, Some).count(),
+        0
+    );
+    let named: IteratedSteps<_, _> = p.process3_iter(Some, Some);
+    assert_eq!(named.collect::<Vec<_>>(), [1, 1]);
+}
+
+#[derive(Pipeline)]
+#[pipeline(mode = "stepper", on_none = hook)]
+pub struct Stepped {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mode = "stepper", namespace)]
+struct SteppedResult<'a, T: Clone + PartialEq + std::fmt::Debug = u8> {
+    value: Result<&'a T, String>,
+}
+
+#[test]
+fn stepper_mode() {
+    let p = Stepped { value: Some(1) };
+    let mut s = p.process3_stepper(|v| Some(v + 1), |v| Some(v * 10));
+    assert_eq!(s.step(), SteppedState::Pending);
+    assert_eq!(s.step(), SteppedState::Done(Some(20)));
+    let mut s = p.process3_stepper(|_| None, |v| Some(v * 10));
+    assert_eq!(s.step(), SteppedState::Done(None));
+    assert_eq!(HOOK.with(Cell::get), 1);
+    let mut s = Stepped { value: None }.process3_stepper(Some, Some);
+    assert_eq!(s.step(), SteppedState::Done(None));
+    assert_eq!(HOOK.with(Cell::get), 0);
+    let x = 3u8;
+    let r = SteppedResult { value: Ok(&x) };
+    let mut s = SteppedResultPipeline::process3_stepper(&r, Ok, |_| Err("late".to_string()));
+    assert_eq!(s.step(), SteppedResultState::Pending);
+    assert_eq!(s.step(), SteppedResultState::Done(Err("late".to_string())));
+    let mut s = Stepped { value: Some(2) }.process3_stepper(Some, |v| Some(v + 1));
+    let mut polls = 0;
+    let result = loop {
+        polls += 1;
+        if let SteppedState::Done(result) = s.step() {
+            break result;
+        }
+    };
+    assert_eq!((result, polls), (Some(3), 2));
+}
+
+#[test]
+#[should_panic(expected = "after the pipeline finished")]
+fn stepper_after_done() {
+    let mut s = Stepped { value: None }.process3_stepper(Some, Some);
+    let _ = s.step();
+    let _ = s.step();
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace)]
+pub struct Spaced {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(
+    namespace,
+    mutate_field,
+    export_trait = "SeedT",
+    accessors,
+    both_receivers
+)]
+struct SpacedGeneric<T: Clone>
+where
+    T: PartialEq,
+{
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace, timeout_field = "ms", timeout_action = "none")]
+struct SpacedTimeout {
+    value: Option<i32>,
+    ms: u32,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace, cow)]
+struct SpacedCow {
+    value: Result<String, String>,
+}
+
+#[test]
+fn namespace() {
+    let s = Spaced { value: Some(1) };
+    assert_eq!(
+        SpacedPipeline::process3(&s, |v| Some(v + 1), |v| Some(v * 2)),
+        Some(4)
+    );
+    assert_eq!(
+        SpacedPipeline::process3_map(&s, Some, Some, |v| v + 1),
+        Some(2)
+    );
+    let mut g = SpacedGeneric { value: Some(2u8) };
+    let doubled = SpacedGenericPipeline::process3(&mut g, |v| Some(v + 1), |v| Some(v * 2));
+    assert_eq!(doubled, Some(6));
+    assert_eq!(g.value, Some(6));
+    SpacedGenericPipeline::set(&mut g, 1);
+    // The exported trait's methods are still called on the struct
+    assert_eq!(g.process3(|v| Some(v + 1), Some), Some(2));
+    assert_eq!(SpacedGenericPipeline::into_process3(g, Some, Some), Some(1));
+    let t = SpacedTimeout {
+        value: Some(1),
+        ms: 1000,
+    };
+    assert_eq!(SpacedTimeoutPipeline::process3(&t, Some, Some), Some(1));
+    let c = SpacedCow {
+        value: Ok("a".into()),
+    };
+    assert_eq!(
+        SpacedCowPipeline::process3(&c, Ok, Ok)
+            .unwrap()
+            .into_owned(),
+        "a"
+    );
+}
+
+struct Opaque;
+
+#[derive(Pipeline)]
+#[pipeline(derive_debug)]
+struct Debugged<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(derive_debug)]
+struct r#DebuggedRaw {
+    r#type: Result<i32, String>,
+}
+
+#[test]
+fn derive_debug() {
+    assert_eq!(
+        format!(
+            "{:?}",
+            Debugged {
+                value: Some(Opaque)
+            }
+        ),
+        "Debugged { value: Some(..) }"
+    );
+    assert_eq!(
+        format!("{:?}", Debugged::<Opaque> { value: None }),
+        "Debugged { value: None }"
+    );
+    assert_eq!(
+        format!("{:?}", DebuggedRaw { r#type: Ok(1) }),
+        "DebuggedRaw { type: Ok(..) }"
+    );
+    let err = DebuggedRaw {
+        r#type: Err("x".into()),
+    };
+    assert_eq!(format!("{err:?}"), "DebuggedRaw { type: Err(..) }");
+    let pretty = format!("{:#?}", Debugged { value: Some(1) });
+    assert_eq!(pretty, "Debugged {\n    value: Some(..),\n}");
+}
+
+#[derive(Pipeline)]
+#[pipeline(from_option)]
+struct Converted<T: Clone = i32> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(from_option)]
+struct ConvertedResult {
+    value: Result<i32, String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(from_option, namespace)]
+struct ConvertedShared {
+    value: Arc<Mutex<Option<i32>>>,
+}
+
+#[test]
+fn from_option() {
+    let p: Converted = Some(1).into();
+    assert_eq!(p.process3(|v| Some(v + 1), Some), Some(2));
+    assert_eq!(Converted::<u8>::from(None).value, None);
+    let r = ConvertedResult::from(Err("e".to_string()));
+    assert_eq!(r.process3(Ok, Ok), Err("e".to_string()));
+    let s = ConvertedShared::from(Some(3));
+    assert_eq!(ConvertedSharedPipeline::process3(&s, Some, Some), Some(3));
+}
@@ -0,0 +1,392 @@
+//! Observing runs: the `on_none` hook, `trace_to` sinks, `stop_on_err_log`, step names and
+//! timeouts.
+
+use pipeline_derive::Pipeline;
+use std::cell::RefCell;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+thread_local! {
+    static STOPS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    static CALLS: RefCell<usize> = const { RefCell::new(0) };
+    static SEEN: RefCell<Vec<i32>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(step: usize) {
+    STOPS.with(|s| s.borrow_mut().push(step));
+}
+
+fn stops() -> Vec<usize> {
+    STOPS.with(|s| s.take())
+}
+
+fn count() {
+    CALLS.with(|c| *c.borrow_mut() += 1);
+}
+
+fn calls() -> usize {
+    CALLS.with(|c| c.take())
+}
+
+fn sink(value: &i32) {
+    SEEN.with(|s| s.borrow_mut().push(*value));
+}
+
+fn seen() -> Vec<i32> {
+    SEEN.with(|s| s.take())
+}
+
+fn slow(v: i32) -> Option<i32> {
+    sleep(Duration::from_millis(30));
+    Some(v + 1)
+}
+
+#[derive(Pipeline)]
+#[pipeline(on_none = record, mutate_field, apply_all)]
+struct Hooked {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(on_none = crate::record, cow)]
+struct HookedCow {
+    value: Option<i32>,
+}
+
+#[test]
+fn on_none() {
+    let mut p = Hooked { value: Some(1) };
+    assert_eq!(
+        p.process4(|v| Some(v + 1), |_| None, |_| panic!("step ran")),
+        None
+    );
+    assert_eq!(p.value, Some(2));
+    assert_eq!(p.process3(Some, Some), Some(2));
+    p.value = None;
+    assert_eq!(p.process3(Some, Some), None);
+    assert_eq!(HookedCow { value: Some(1) }.process3(|_| None, Some), None);
+    assert_eq!(stops(), [2, 0, 1]);
+}
+
+#[derive(Pipeline)]
+#[pipeline(on_none = count, version = 2)]
+struct Counted {
+    value: Option<i32>,
+}
+
+#[test]
+fn on_none_without_index() {
+    let p = Counted { value: Some(1) };
+    assert_eq!(p.process([Some, |_| None, Some]), None);
+    assert_eq!(p.process([Some]), Some(1));
+    assert_eq!(Counted { value: None }.process([Some]), None);
+    assert_eq!(calls(), 2);
+}
+
+#[test]
+fn on_none_in_variants() {
+    let mut p = Hooked { value: Some(1) };
+    let _: Vec<i32> = p.process3_collect(Some, |_| None);
+    assert_eq!(p.process3_tuple(|_| None, Some), (None, None));
+    assert_eq!(p.process3_with_count(Some, |_| None), (None, 2));
+    assert_eq!(p.apply_all(|v| (v < 3).then_some(v + 1), 10), None);
+    assert_eq!(stops(), [2, 1, 2, 3]);
+}
+
+#[derive(Pipeline)]
+#[pipeline(trace_to = sink, both_receivers)]
+struct Traced {
+    value: Option<i32>,
+}
+
+mod sinks {
+    pub fn generic<T: std::fmt::Debug>(value: &T) {
+        let _ = format!("{value:?}");
+    }
+}
+
+#[derive(Pipeline)]
+#[pipeline(trace_to = sink, cow)]
+struct TracedCow {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(trace_to = sinks::generic, version = 2)]
+struct TracedGeneric<T: std::fmt::Debug> {
+    value: Result<T, ()>,
+}
+
+#[test]
+fn trace_to() {
+    let p = Traced { value: Some(1) };
+    assert_eq!(p.process4(|v| Some(v + 1), |_| None, Some), None);
+    assert_eq!(seen(), [2]);
+    assert_eq!(p.process3_from(10, Some, |v| Some(v * 2)), Some(20));
+    assert_eq!(seen(), [10, 20]);
+    assert_eq!(Traced { value: Some(3) }.into_process3(Some, Some), Some(3));
+    assert_eq!(seen(), [3, 3]);
+    let c = TracedCow { value: Some(7) };
+    assert_eq!(c.process3(Some, Some).map(|v| *v), Some(7));
+    assert_eq!(seen(), [7, 7]);
+    assert_eq!(
+        TracedGeneric { value: Ok("a") }.process([Ok::<&str, ()>]),
+        Ok("a")
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(stop_on_err_log, both_receivers, step_names = ["parse", "check", "scale"])]
+struct Logged<E> {
+    value: Result<i32, E>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(stop_on_err_log, version = 2)]
+struct LoggedV2 {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn stop_on_err_log_child() {
+    // Run by `stop_on_err_log_logs_failing_step_once`, which checks its stderr
+    if std::env::var_os("PIPELINE_LOG_CHILD").is_none() {
+        return;
+    }
+    let p = Logged {
+        value: Ok::<i32, &str>(1),
+    };
+    assert_eq!(p.process4(Ok, |_| Err("bad"), Ok), Err("bad"));
+    assert_eq!(
+        Logged {
+            value: Err::<i32, &str>("seed")
+        }
+        .process3(Ok, Ok),
+        Err("seed")
+    );
+    let _: Vec<i32> = p.process3_collect(|_| Err("collect"), Ok);
+    assert_eq!(p.process3_from(1, Ok, |_| Err("from")), Err("from"));
+    let v = LoggedV2 { value: Ok(1) };
+    assert_eq!(
+        v.process([Ok, |_: i32| Err("v2".to_string())]),
+        Err("v2".into())
+    );
+}
+
+#[test]
+fn stop_on_err_log_logs_failing_step_once() {
+    let out = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "stop_on_err_log_child",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env("PIPELINE_LOG_CHILD", "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(out.status.success(), "{stderr}");
+    let lines: Vec<&str> = stderr
+        .lines()
+        .filter(|l| l.starts_with("Pipeline"))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            "Pipeline step check failed: \"bad\"",
+            "Pipeline step parse failed: \"collect\"",
+            "Pipeline step check failed: \"from\"",
+            "Pipeline step 2 failed: \"v2\"",
+        ]
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(max_arity = 3, step_names = ["parse", "validate"], on_none = record)]
+struct Named {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(step_names = ["a", "b", "c"], on_none = record, both_receivers)]
+struct NamedWide {
+    value: Option<i32>,
+}
+
+#[test]
+fn step_names() {
+    // The hook still receives run positions, which index into `steps()`
+    let p = Named { value: Some(1) };
+    assert_eq!(p.process3(Some, |_| None), None);
+    assert_eq!(p.process3_rev(Some, |_| None), None);
+    assert_eq!(p.process3_rev(|_| None, Some), None);
+    assert_eq!(Named { value: None }.process3(Some, Some), None);
+    assert_eq!(stops(), [2, 1, 2, 0]);
+    assert_eq!(Named::steps()[2 - 1], "validate");
+    let q = NamedWide { value: Some(1) };
+    assert_eq!(q.process4(Some, Some, |_| None), None);
+    assert_eq!(q.process4_from(1, Some, |_| None, Some), None);
+    assert_eq!(
+        NamedWide { value: Some(1) }.into_process3(|_| None, Some),
+        None
+    );
+    assert_eq!(stops(), [3, 2, 1]);
+}
+
+#[derive(Pipeline)]
+#[pipeline(step_names = ["parse", "check", "emit"])]
+struct Listed<T: Clone> {
+    value: Result<T, String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(step_names = ["a", "b"], max_arity = 3, namespace)]
+struct ListedNamespace {
+    value: Option<i32>,
+}
+
+#[test]
+fn steps() {
+    assert_eq!(Listed::<u8>::steps(), ["parse", "check", "emit"]);
+    assert_eq!(ListedNamespacePipeline::steps(), ["a", "b"]);
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 10, timeout_action = "none", on_none = record, both_receivers)]
+struct TimeoutNone {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 10, timeout_action = "none", version = 2)]
+struct TimeoutNoneV2 {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 10, error_enum = "TimeoutDefaultError")]
+struct TimeoutDefault {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 10)]
+struct TimeoutDefaultResult {
+    value: Result<i32, ()>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 10, timeout_action = "panic")]
+struct TimeoutPanic {
+    value: Result<i32, ()>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout = 10, timeout_action = "log")]
+struct TimeoutLog {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(max_arity = 3, step_names = ["first", "second"], timeout = 1, timeout_action = "panic")]
+struct TimeoutNamed {
+    value: Result<i32, ()>,
+}
+
+#[test]
+fn timeout_none() {
+    let p = TimeoutNone { value: Some(1) };
+    assert_eq!(p.process3(slow, Some), None);
+    // The deadline is only checked before a step, so a slow last step still finishes
+    assert_eq!(p.process4(Some, Some, slow), Some(2));
+    assert_eq!(p.process3_tuple(slow, Some), (Some(2), None));
+    assert_eq!(p.process3_from(5, slow, Some), None);
+    assert_eq!(
+        TimeoutNone { value: Some(1) }.into_process3(slow, Some),
+        None
+    );
+    assert_eq!(stops(), [2, 2, 2, 2]);
+    assert_eq!(TimeoutNoneV2 { value: Some(1) }.process([slow, slow]), None);
+}
+
+#[test]
+fn timeout_default() {
+    // `Option` fields stop as with `timeout_action = "none"`
+    let p = TimeoutDefault { value: Some(1) };
+    assert_eq!(p.process3(slow, Some), None);
+    let stopped = p.try_process3(slow, Some);
+    assert_eq!(stopped, Err(TimeoutDefaultError::Timeout { step: 2 }));
+    // `Result` fields have no `None` to stop with, so they run every step
+    let r = TimeoutDefaultResult { value: Ok(1) };
+    assert_eq!(r.process3(|v| slow(v).ok_or(()), Ok), Ok(2));
+}
+
+#[test]
+#[should_panic(expected = "Pipeline timeout of 10 ms exceeded before step 2")]
+fn timeout_panic() {
+    let _ = TimeoutPanic { value: Ok(1) }.process3(|v| slow(v).ok_or(()), Ok);
+}
+
+#[test]
+#[should_panic(expected = "exceeded before step second")]
+fn timeout_panic_names_step() {
+    let _ = TimeoutNamed { value: Ok(1) }.process3(|v| slow(v).ok_or(()), Ok);
+}
+
+#[test]
+fn timeout_log() {
+    assert_eq!(TimeoutLog { value: Some(1) }.process3(slow, slow), Some(3));
+}
+
+#[derive(Pipeline)]
+#[pipeline(
+    timeout_field = "deadline_ms",
+    timeout_action = "none",
+    both_receivers,
+    mutate_field,
+    as_fn
+)]
+struct FieldTimeout {
+    value: Option<i32>,
+    deadline_ms: u32,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout_field = "limit", timeout_action = "log", cow)]
+struct FieldTimeoutResult<T> {
+    limit: u16,
+    value: Result<T, String>,
+}
+
+#[test]
+fn timeout_field() {
+    let mut p = FieldTimeout {
+        value: Some(1),
+        deadline_ms: 1000,
+    };
+    assert_eq!(p.process3(slow, slow), Some(3));
+    p.deadline_ms = 5;
+    p.value = Some(1);
+    assert_eq!(p.process3(slow, slow), None);
+    assert_eq!(p.process3_from(1, slow, slow), None);
+    // `as_fn` reads the field when it builds the closure
+    let f = p.as_fn(slow, slow);
+    p.deadline_ms = 1000;
+    assert_eq!(f(1), None);
+    assert_eq!(p.as_fn(slow, slow)(1), Some(3));
+    assert_eq!(
+        FieldTimeout {
+            value: Some(1),
+            deadline_ms: 0
+        }
+        .into_process3(slow, slow),
+        None
+    );
+    let r = FieldTimeoutResult {
+        limit: 1,
+        value: Ok::<i32, String>(1),
+    };
+    assert_eq!(r.process3(Ok, Ok).map(|v| *v), Ok(1));
+}
@@ -0,0 +1,58 @@
+//! The generated code must compile warning-free in the deriving crate, also when the
+//! struct is private and only a few of the generated methods are used.
+#![deny(warnings)]
+
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+struct Private {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace)]
+struct Namespaced {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(
+    accessors,
+    config,
+    mode = "stepper",
+    export_trait = "HelpersTrait",
+    error_enum = "HelpersError"
+)]
+struct Helpers {
+    value: Option<i32>,
+}
+
+#[test]
+fn private_struct_using_only_process3_and_process4() {
+    let p = Private { value: Some(1) };
+    assert_eq!(p.process3(|v| Some(v + 1), |v| Some(v * 2)), Some(4));
+    assert_eq!(p.process4(Some, |v| Some(v + 1), |v| Some(v * 3)), Some(6));
+}
+
+#[test]
+fn private_namespace_using_only_process3() {
+    let p = Namespaced { value: Some(1) };
+    assert_eq!(
+        NamespacedPipeline::process3(&p, Some, |v| Some(v + 1)),
+        Some(2)
+    );
+}
+
+#[test]
+fn private_helpers_used_in_part() {
+    let mut p = Helpers { value: None };
+    p.set(2);
+    assert_eq!(p.run(HelpersConfig::new().step(|v| Some(v + 1))), Some(3));
+    let mut stepper = p.process3_stepper(Some, |v| Some(v * 2));
+    assert!(matches!(stepper.step(), HelpersState::Pending));
+    assert!(matches!(stepper.step(), HelpersState::Done(Some(4))));
+    assert_eq!(
+        p.try_process3(|_| None, Some),
+        Err(HelpersError::EarlyStop { step: 1 })
+    );
+}
@@ -0,0 +1,492 @@
+//! The fixed-arity `process3`, `process4`, ... methods and the options changing how they
+//! receive, run and store the value.
+
+use pipeline_derive::Pipeline;
+use std::borrow::Cow;
+use std::cell::Cell;
+
+#[derive(Debug, PartialEq)]
+struct NoClone(i32);
+
+#[derive(Pipeline)]
+#[pipeline(field_name)]
+struct Plain {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+struct Fallible<T, E> {
+    value: Result<T, E>,
+}
+
+#[test]
+fn process3_and_process4() {
+    let p = Plain { value: Some(2) };
+    assert_eq!(p.process3(|v| Some(v + 1), |v| Some(v * 10)), Some(30));
+    assert_eq!(
+        p.process4(|v| Some(v + 1), |_| None, |_| panic!("step ran")),
+        None
+    );
+    assert_eq!(Plain { value: None }.process3(Some, Some), None);
+    // The struct is only borrowed
+    assert_eq!(p.value, Some(2));
+}
+
+#[test]
+fn steps_borrowing_the_struct() {
+    let p = Plain { value: Some(2) };
+    let offset = String::from("abc");
+    let scaled = p.process3(
+        |v| Some(v + offset.len() as i32),
+        |v| p.value.map(|w| w * v),
+    );
+    assert_eq!(scaled, Some(10));
+    let mut calls = 0;
+    let counted = p.process4(
+        |v| {
+            calls += 1;
+            Some(v)
+        },
+        |v| Some(v + p.value?),
+        Some,
+    );
+    assert_eq!((counted, calls), (Some(4), 1));
+}
+
+#[test]
+fn result_field() {
+    let r: Fallible<i32, String> = Fallible { value: Ok(1) };
+    assert_eq!(r.process3(|v| Ok(v + 1), |v| Ok(v * 3)), Ok(6));
+    let failed = r.process4(|v| Ok(v + 1), |_| Err("bad".to_string()), |v| Ok(v * 3));
+    assert_eq!(failed, Err("bad".to_string()));
+    let e: Fallible<i32, String> = Fallible {
+        value: Err("seed".into()),
+    };
+    assert_eq!(e.process3(Ok, Ok), Err("seed".to_string()));
+}
+
+#[test]
+fn rev() {
+    let p = Plain { value: Some(2) };
+    assert_eq!(p.process3_rev(|v| Some(v + 1), |v| Some(v * 10)), Some(21));
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip)]
+struct Skipped {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(both_receivers)]
+struct FallibleBoth {
+    value: Result<i32, ()>,
+}
+
+#[test]
+fn from_start_value() {
+    let p = Plain { value: Some(1) };
+    let inc = |v: i32| Some(v + 1);
+    assert_eq!(p.process3_from(10, inc, Some), Some(11));
+    assert_eq!(p.process4_from(10, inc, inc, |_| None), None);
+    assert_eq!(
+        Skipped { value: Some(1) }.process3_from(1, Some, Some),
+        None
+    );
+    assert_eq!(
+        FallibleBoth { value: Ok(1) }.process3_from(5, Ok, Ok),
+        Ok(5)
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(mutate_field)]
+struct Mutated {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mutate_field)]
+struct MutatedResult {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn mutate_field() {
+    let mut p = Mutated { value: Some(1) };
+    assert_eq!(
+        p.process4(|v| Some(v + 1), |v| Some(v * 10), |_| None),
+        None
+    );
+    assert_eq!(p.value, Some(20));
+    let mut r = MutatedResult { value: Ok(1) };
+    assert_eq!(
+        r.process3(|v| Ok(v + 1), |_| Err("no".into())),
+        Err("no".to_string())
+    );
+    assert_eq!(r.value, Ok(2));
+}
+
+#[derive(Pipeline)]
+#[pipeline(mutate_field)]
+struct MutatedRef<'a> {
+    value: Option<&'a String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mutate_field)]
+struct MutatedGenericRef<'a, T: 'a>
+where
+    T: Clone,
+{
+    value: Option<&'a T>,
+}
+
+#[test]
+fn mutate_field_stores_borrowed_values() {
+    let (a, b) = ("a".to_string(), "b".to_string());
+    let mut p = MutatedRef { value: Some(&a) };
+    assert_eq!(p.process3(|_| Some(&b), |_| None), None);
+    assert_eq!(p.value, Some(&b));
+    let mut g = MutatedGenericRef { value: Some(&1) };
+    assert_eq!(g.process3(|_| Some(&2), Some), Some(&2));
+    assert_eq!(g.value, Some(&2));
+}
+
+#[test]
+fn mutate_field_keeps_value_after_panic() {
+    let mut p = Mutated { value: Some(1) };
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        p.process3(
+            |v| Some(v + 4),
+            |_| -> Option<i32> { panic!("step failed") },
+        )
+    }));
+    assert!(result.is_err());
+    assert_eq!(p.value, Some(5));
+}
+
+#[derive(Pipeline)]
+#[pipeline(both_receivers, mutate_field)]
+struct Both<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(both_receivers)]
+struct BothResult<T> {
+    value: Result<T, String>,
+}
+
+#[test]
+fn both_receivers() {
+    let mut p = Both { value: Some(1) };
+    assert_eq!(p.process3(|v| Some(v + 1), Some), Some(2));
+    assert_eq!(p.into_process4(|v| Some(v + 1), Some, Some), Some(3));
+    let n = Both {
+        value: Some(NoClone(1)),
+    };
+    assert_eq!(
+        n.into_process3(|v| Some(NoClone(v.0 + 1)), Some),
+        Some(NoClone(2))
+    );
+    let r = BothResult {
+        value: Ok(NoClone(5)),
+    };
+    assert_eq!(
+        r.into_process3(Ok, |_| Err("e".into())),
+        Err("e".to_string())
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(take)]
+struct Taken<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(take, skip)]
+struct TakenSkipped {
+    value: Option<i32>,
+}
+
+#[test]
+fn take() {
+    let mut p = Taken {
+        value: Some(NoClone(1)),
+    };
+    assert_eq!(
+        p.process3_take(|v| Some(NoClone(v.0 + 1)), Some),
+        Some(NoClone(2))
+    );
+    assert!(p.value.is_none());
+    assert!(p.process3_take(Some, Some).is_none());
+    let mut q = Taken { value: Some(1) };
+    assert_eq!(q.process3_take(|_| None::<i32>, Some), None);
+    assert_eq!(q.value, None);
+    let mut s = TakenSkipped { value: Some(1) };
+    assert_eq!(s.process3_take(Some, Some), None);
+    assert_eq!(s.value, Some(1));
+}
+
+#[derive(Pipeline)]
+#[pipeline(cow)]
+struct Borrowed {
+    value: Option<String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(cow, min_steps = 3)]
+struct BorrowedResult<T: Clone> {
+    value: Result<T, ()>,
+}
+
+#[test]
+fn cow() {
+    let p = Borrowed {
+        value: Some("hi".into()),
+    };
+    let exclaimed = p.process3(Some, |s| Some(Cow::Owned(format!("{s}!"))));
+    assert_eq!(exclaimed.map(Cow::into_owned), Some("hi!".to_string()));
+    let unchanged = p.process3(Some, Some).unwrap();
+    assert!(matches!(unchanged, Cow::Borrowed(_)));
+    let q = BorrowedResult { value: Ok(vec![1]) };
+    assert_eq!(q.process4(Ok, Ok, Ok).map(Cow::into_owned), Ok(vec![1]));
+}
+
+#[derive(Pipeline)]
+#[pipeline(accessors)]
+struct Accessors<T> {
+    value: Option<T>,
+}
+
+#[test]
+fn accessors() {
+    let mut p = Accessors { value: Some(1) };
+    p.clear();
+    assert_eq!(p.value, None);
+    p.set(4);
+    assert_eq!(p.process3(|v| Some(v + 1), Some), Some(5));
+    let mut n = Accessors { value: None };
+    n.set(NoClone(1));
+    assert_eq!(n.value, Some(NoClone(1)));
+}
+
+#[derive(Pipeline)]
+#[pipeline(peek, into_parts, skip)]
+struct Peeked<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(peek, into_parts)]
+struct PeekedResult {
+    value: Result<String, u8>,
+}
+
+#[test]
+fn peek() {
+    let p = Peeked {
+        value: Some(NoClone(3)),
+    };
+    assert_eq!(p.peek(), Some(&NoClone(3)));
+    let r = PeekedResult {
+        value: Ok("a".into()),
+    };
+    assert_eq!(r.peek(), Ok(&"a".to_string()));
+    assert_eq!(PeekedResult { value: Err(2) }.peek(), Err(&2));
+}
+
+#[test]
+fn into_parts() {
+    assert_eq!(
+        Peeked {
+            value: Some(NoClone(1))
+        }
+        .into_parts(),
+        Some(NoClone(1))
+    );
+    assert_eq!(PeekedResult { value: Err(2) }.into_parts(), Err(2));
+}
+
+#[derive(Pipeline)]
+#[pipeline(first_some, max_arity = 5)]
+struct FirstSome {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(first_some, cow)]
+struct FirstOk {
+    value: Result<String, String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(first_some, mutate_field)]
+struct FirstSomeMutated {
+    value: Option<i32>,
+}
+
+#[test]
+fn first_some() {
+    let p = FirstSome { value: Some(5) };
+    let tried = Cell::new(0);
+    let attempt = |result: Option<i32>| {
+        tried.set(tried.get() + 1);
+        result
+    };
+    let found = p.process4_first_some(
+        |v| attempt((v > 10).then_some(v)),
+        |v| attempt(Some(v * 2)),
+        |_| attempt(Some(0)),
+    );
+    assert_eq!((found, tried.get()), (Some(10), 2));
+    assert_eq!(p.process3_first_some(|_| None, |_| None), None);
+    assert_eq!(
+        FirstSome { value: None }.process5_first_some(Some, Some, Some, Some),
+        None
+    );
+    let r = FirstOk {
+        value: Ok("a".into()),
+    };
+    let failed = r.process3_first_some(|_| Err("one".into()), |_| Err("two".into()));
+    assert_eq!(failed.map(Cow::into_owned), Err("two".to_string()));
+    let passed = r.process3_first_some(|_| Err("one".into()), Ok);
+    assert_eq!(passed.map(Cow::into_owned), Ok("a".to_string()));
+    let mut m = FirstSomeMutated { value: Some(1) };
+    assert_eq!(m.process3_first_some(|_| None, |v| Some(v + 1)), Some(2));
+    assert_eq!(m.value, Some(2));
+}
+
+#[derive(Pipeline)]
+#[pipeline(eq_check, max_arity = 5)]
+struct EqChecked {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(eq_check, version = 2, namespace)]
+struct EqCheckedV2<T: Clone> {
+    value: Result<T, String>,
+}
+
+#[test]
+fn eq_check() {
+    let calls = Cell::new(0);
+    let counted = |v: i32| {
+        calls.set(calls.get() + 1);
+        Some(v * 2)
+    };
+    let p = EqChecked { value: Some(4) };
+    // The first step leaves 4 unchanged, so the second never runs
+    assert_eq!(p.process3(Some, counted), Some(4));
+    assert_eq!(p.process4(|v| Some(v + 1), Some, counted), Some(5));
+    assert_eq!(calls.get(), 0);
+    assert_eq!(
+        p.process4(|v| Some(v + 1), |v| Some(v + 1), counted),
+        Some(12)
+    );
+    assert_eq!(calls.get(), 1);
+    // Other methods run every step
+    assert_eq!(p.process3_rev(|v| Some(v * 2), Some), Some(8));
+    let v = EqCheckedV2 { value: Ok(3u8) };
+    type Step = Box<dyn FnOnce(u8) -> Result<u8, String>>;
+    let steps: Vec<Step> = vec![Box::new(Ok), Box::new(|_| Err("ran".into()))];
+    assert_eq!(EqCheckedV2Pipeline::process(&v, steps), Ok(3));
+    let steps: Vec<Step> = vec![Box::new(|v| Ok(v + 1)), Box::new(|_| Err("ran".into()))];
+    assert_eq!(
+        EqCheckedV2Pipeline::process(&v, steps),
+        Err("ran".to_string())
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(field_name)]
+struct r#Raw {
+    r#type: Option<i32>,
+}
+
+macro_rules! named_field {
+    ($name:ident, $field:ident) => {
+        #[derive(Pipeline)]
+        #[pipeline(field_name)]
+        struct $name {
+            $field: Option<i32>,
+        }
+    };
+}
+
+named_field!(Payload, payload);
+
+#[test]
+fn field_name() {
+    assert_eq!(Plain::FIELD_NAME, "value");
+    assert_eq!(Raw::FIELD_NAME, "type");
+    assert_eq!(Payload::FIELD_NAME, "payload");
+    const NAME: &str = Plain::FIELD_NAME;
+    assert_eq!(NAME.len(), 5);
+}
+
+// Methods with common names are only generated on request, so they cannot clash with the
+// struct's own
+#[derive(Pipeline)]
+struct OwnMethods {
+    value: Option<i32>,
+}
+
+impl OwnMethods {
+    const FIELD_NAME: &'static str = "own";
+
+    fn peek(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn steps() -> usize {
+        2
+    }
+
+    fn into_parts(self) -> (bool, Option<i32>) {
+        (self.value.is_some(), self.value)
+    }
+
+    fn run_dyn(&self, steps: usize) -> usize {
+        steps
+    }
+
+    fn compose(&self, other: i32) -> Option<i32> {
+        self.value.map(|v| v + other)
+    }
+
+    fn apply_all(&self) -> bool {
+        true
+    }
+
+    fn as_fn(&self) -> i32 {
+        self.value.unwrap_or_default()
+    }
+
+    fn with_step(&self, step: i32) -> i32 {
+        step
+    }
+
+    fn process_mixed(&self) -> &'static str {
+        "mixed"
+    }
+}
+
+#[test]
+fn own_methods_with_generated_names() {
+    let p = OwnMethods { value: Some(1) };
+    assert!(p.peek());
+    assert_eq!(OwnMethods::steps(), 2);
+    assert_eq!(p.run_dyn(3), 3);
+    assert_eq!(p.compose(2), Some(3));
+    assert!(p.apply_all());
+    assert_eq!(p.as_fn(), 1);
+    assert_eq!(p.with_step(4), 4);
+    assert_eq!(p.process_mixed(), "mixed");
+    assert_eq!(OwnMethods::FIELD_NAME, "own");
+    assert_eq!(p.process3(Some, Some), Some(1));
+    assert_eq!(p.into_parts(), (true, Some(1)));
+}
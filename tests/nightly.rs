@@ -0,0 +1,28 @@
+//! `#[pipeline(generator)]`, available with the `nightly` Cargo feature on a nightly compiler.
+#![cfg(feature = "nightly")]
+#![feature(gen_blocks)]
+
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+#[pipeline(generator)]
+struct Generated {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(generator)]
+struct GeneratedResult {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn generator() {
+    let p = Generated { value: Some(1) };
+    let values: Vec<i32> = p.process3_gen(|v| Some(v + 1), |v| Some(v * 10)).collect();
+    assert_eq!(values, [2, 20]);
+    assert_eq!(p.process3_gen(|_| None, Some).count(), 0);
+    let r = GeneratedResult { value: Ok(1) };
+    let values: Vec<i32> = r.process3_gen(|v| Ok(v + 1), |_| Err("e".into())).collect();
+    assert_eq!(values, [2]);
+}
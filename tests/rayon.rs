@@ -0,0 +1,33 @@
+//! `#[pipeline(par_iter)]`, available with the `rayon` Cargo feature.
+#![cfg(feature = "rayon")]
+
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+#[pipeline(par_iter)]
+struct Batch {
+    value: Option<Vec<i32>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(par_iter, skip)]
+struct SkippedBatch {
+    value: Option<Vec<i32>>,
+}
+
+#[test]
+fn par_iter() {
+    let b = Batch {
+        value: Some((1..=6).collect()),
+    };
+    let even = |v: i32| (v % 2 == 0).then_some(v);
+    assert_eq!(
+        b.process3_par_iter(even, |v| Some(v * 10)),
+        Some(vec![20, 40, 60])
+    );
+    assert_eq!(Batch { value: None }.process3_par_iter(even, Some), None);
+    let s = SkippedBatch {
+        value: Some(vec![1]),
+    };
+    assert_eq!(s.process3_par_iter(Some, Some), None);
+}
@@ -0,0 +1,74 @@
+//! `#[pipeline(serde)]`, available with the `serde` Cargo feature.
+#![cfg(feature = "serde")]
+
+use pipeline_derive::Pipeline;
+use std::sync::{Arc, Mutex};
+
+#[derive(Pipeline)]
+#[pipeline(serde)]
+struct Dto<T> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(serde, timeout = 1000)]
+struct ResultDto {
+    value: std::result::Result<i32, String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(serde, version = 2)]
+struct SharedDto {
+    value: Arc<Mutex<Result<String, String>>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(serde, from_option)]
+struct ConvertedDto {
+    value: Option<i32>,
+}
+
+macro_rules! named {
+    ($name:ident) => {
+        #[derive(Pipeline)]
+        #[pipeline(serde, mode = "iterator", namespace)]
+        struct $name {
+            value: Option<i32>,
+        }
+    };
+}
+
+named!(NamedDto);
+
+#[test]
+fn roundtrip() {
+    let d = Dto::from_inner(Some(5));
+    assert_eq!(d.to_inner(), Some(5));
+    assert_eq!(Dto::from_inner(d.to_inner()).process3(Some, Some), Some(5));
+}
+
+#[test]
+fn result_field() {
+    let r = ResultDto::from_inner(Err("e".into()));
+    assert_eq!(r.process3(Ok, Ok), Err("e".to_string()));
+    assert_eq!(r.to_inner(), Err("e".to_string()));
+}
+
+#[test]
+fn shared_field() {
+    let r = SharedDto::from_inner(Ok("x".into()));
+    let appended = r.process([|s: String| Ok::<String, String>(s + "y")]);
+    assert_eq!(appended, Ok("xy".to_string()));
+    assert_eq!(r.to_inner(), Ok("x".to_string()));
+}
+
+#[test]
+fn with_other_helpers() {
+    assert_eq!(
+        ConvertedDto::from(Some(4)).value,
+        ConvertedDto::from_inner(Some(4)).value
+    );
+    let n = NamedDto::from_inner(Some(1));
+    assert_eq!(n.to_inner(), Some(1));
+    assert_eq!(NamedDtoPipeline::process3_iter(&n, Some, Some).count(), 2);
+}
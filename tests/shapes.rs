@@ -0,0 +1,800 @@
+//! Struct and field shapes the derive accepts.
+
+use pipeline_derive::Pipeline;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Pipeline)]
+struct Node {
+    value: Option<Box<Node>>,
+}
+
+#[test]
+fn boxed_recursion() {
+    let leaf = Node { value: None };
+    assert!(leaf.process3(Some, Some).is_none());
+    let root = Node {
+        value: Some(Box::new(leaf)),
+    };
+    assert!(root.process3(|n| n.value, Some).is_none());
+}
+
+#[derive(Pipeline)]
+#[pipeline(mutate_field, accessors, take, derive_debug, max_arity = 3)]
+struct Shared {
+    value: Arc<Mutex<Option<i32>>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(export_trait = "Counter")]
+struct SharedReadOnly<T> {
+    value: std::sync::Arc<std::sync::Mutex<Option<T>>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(version = 2)]
+struct SharedResult {
+    value: Arc<Mutex<Result<String, String>>>,
+}
+
+#[test]
+fn shared_field() {
+    let cell = Arc::new(Mutex::new(Some(0)));
+    let shared = Arc::new(Shared {
+        value: Arc::clone(&cell),
+    });
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    shared.process3(Some, |v| Some(v + 1));
+                }
+            })
+        })
+        .collect();
+    handles.into_iter().for_each(|h| h.join().unwrap());
+    // Runs overlap, so some increments may be lost, but the lock keeps every write whole
+    let n = cell.lock().unwrap().unwrap();
+    assert!((10..=40).contains(&n));
+    assert_eq!(format!("{shared:?}"), "Shared { value: Some(..) }");
+    let mut s = Shared {
+        value: Arc::new(Mutex::new(Some(1))),
+    };
+    assert_eq!(s.process3(|v| Some(v * 5), |_| None), None);
+    assert_eq!(*s.value.lock().unwrap(), Some(5));
+    assert_eq!(s.process3_take(Some, Some), Some(5));
+    assert_eq!(*s.value.lock().unwrap(), None);
+    s.set(3);
+    assert_eq!(s.process3_history(Some, Some), Some((3, vec![3, 3])));
+    let r = SharedReadOnly {
+        value: Arc::new(Mutex::new(Some("a"))),
+    };
+    assert_eq!(Counter::process3(&r, Some, Some), Some("a"));
+    assert_eq!(r.process4(Some, Some, |_| None::<&str>), None);
+    assert_eq!(*r.value.lock().unwrap(), Some("a"));
+    let r = SharedResult {
+        value: Arc::new(Mutex::new(Ok("x".into()))),
+    };
+    let appended = r.process([|s: String| Ok::<String, String>(s + "y")]);
+    assert_eq!(appended, Ok("xy".to_string()));
+}
+
+macro_rules! make {
+    ($name:ident) => {
+        #[derive(Pipeline)]
+        #[pipeline(mutate_field)]
+        struct $name {
+            value: Option<i32>,
+        }
+    };
+}
+
+make!(Made);
+
+macro_rules! make_typed {
+    ($name:ident, $ty:ty $(, $attr:meta)?) => {
+        #[derive(::pipeline_derive::Pipeline)]
+        $(#[pipeline($attr)])?
+        pub struct $name {
+            pub value: $ty,
+        }
+    };
+}
+
+mod typed {
+    make_typed!(TypedOption, Option<i32>);
+    make_typed!(TypedResult, Result<String, u8>);
+    make_typed!(TypedShared, std::sync::Arc<std::sync::Mutex<Option<i32>>>);
+    pub type Elem = u8;
+    make_typed!(TypedAlias, Option<Elem>, namespace);
+}
+
+pub use typed::{TypedAlias, TypedOption, TypedResult, TypedShared};
+
+macro_rules! passthrough {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident { $field:ident: $ty:ty $(,)? }) => {
+        $(#[$meta])* $vis struct $name { $field: $ty }
+    };
+}
+
+passthrough! {
+    #[derive(Pipeline)]
+    #[pipeline(timeout = 1000, step_names = ["a", "b", "c"], error_enum = "PassError", namespace)]
+    pub struct Passed { inner: Option<i32> }
+}
+
+#[test]
+fn macro_generated() {
+    let mut m = Made { value: Some(1) };
+    assert_eq!(m.process3(|v| Some(v + 1), Some), Some(2));
+    assert_eq!(
+        TypedOption { value: Some(1) }.process3(|v| Some(v + 1), Some),
+        Some(2)
+    );
+    let r = TypedResult {
+        value: Ok("a".into()),
+    };
+    assert_eq!(r.process3_all_errors(Ok, |_| Err(2)), Err(vec![2]));
+    let s = TypedShared {
+        value: Arc::new(Mutex::new(Some(3))),
+    };
+    assert_eq!(s.process3(Some, Some), Some(3));
+    let a = TypedAlias { value: Some(1) };
+    assert_eq!(typed::TypedAliasPipeline::process3(&a, Some, Some), Some(1));
+    let n = Passed { inner: Some(1) };
+    assert_eq!(
+        PassedPipeline::process3(&n, |v| Some(v + 1), |v| Some(v * 3)),
+        Some(6)
+    );
+    assert_eq!(
+        PassedPipeline::try_process3(&n, |_| None, Some),
+        Err(PassError::EarlyStop { step: 1 })
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(derive_debug)]
+struct FieldAttributes {
+    /// The stored value.
+    #[allow(unused)]
+    #[cfg_attr(test, doc = "Only documented in tests.")]
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(timeout_field = "ms")]
+struct TimeoutFieldAttributes {
+    #[doc(hidden)]
+    ms: u64,
+    #[allow(unused)]
+    value: Result<i32, String>,
+}
+
+#[test]
+fn attributes_on_the_field() {
+    assert_eq!(
+        FieldAttributes { value: Some(1) }.process3(Some, Some),
+        Some(1)
+    );
+    assert_eq!(
+        format!("{:?}", FieldAttributes { value: None }),
+        "FieldAttributes { value: None }"
+    );
+    assert_eq!(
+        TimeoutFieldAttributes {
+            ms: 1000,
+            value: Ok(1)
+        }
+        .process3(Ok, Ok),
+        Ok(1)
+    );
+}
+
+// Derived only with a feature of this package, which integration tests see like a deriving
+// crate's own
+#[cfg_attr(feature = "serde", derive(Pipeline))]
+struct CfgField {
+    #[cfg(feature = "serde")]
+    value: Option<i32>,
+}
+
+#[cfg(not(feature = "serde"))]
+#[test]
+fn cfg_field_off() {
+    let _ = CfgField {};
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn cfg_field_on() {
+    assert_eq!(CfgField { value: Some(1) }.process3(Some, Some), Some(1));
+}
+
+#[derive(Pipeline)]
+struct Borrowing<'a, T: 'a>
+where
+    T: Clone,
+{
+    value: Option<&'a T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(export_trait = "BorrowingPipeline")]
+struct BorrowingExported<'a, T: 'a>
+where
+    T: Clone,
+{
+    value: Option<&'a T>,
+}
+
+fn positive(v: &i32) -> Option<&i32> {
+    Borrowing { value: Some(v) }.process3(Some, |x| (*x > 0).then_some(x))
+}
+
+#[test]
+fn lifetime_bound_where_clause() {
+    let v = 3;
+    assert_eq!(positive(&v), Some(&3));
+    assert_eq!(positive(&-1), None);
+    let q = BorrowingExported { value: Some(&v) };
+    assert_eq!(BorrowingPipeline::process3(&q, Some, Some), Some(&3));
+}
+
+#[derive(Pipeline)]
+#[pipeline(both_receivers, export_trait = "DefaultedPipeline")]
+struct Defaulted<T = i32> {
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(version = 2)]
+struct DefaultedArray<T: Copy = &'static str, const N: usize = 2>
+where
+    T: Default,
+{
+    value: Option<[T; N]>,
+}
+
+#[test]
+fn defaulted_params() {
+    let p: Defaulted = Defaulted { value: Some(1) };
+    assert_eq!(p.process3(|v| Some(v + 1), Some), Some(2));
+    assert_eq!(DefaultedPipeline::process4(&p, Some, Some, Some), Some(1));
+    assert_eq!(p.into_process3(Some, Some), Some(1));
+    let a: DefaultedArray = DefaultedArray {
+        value: Some(["a", "b"]),
+    };
+    assert_eq!(a.process([Some]), Some(["a", "b"]));
+}
+
+#[derive(Pipeline)]
+struct StdPath {
+    value: std::option::Option<i32>,
+}
+
+#[derive(Pipeline)]
+struct CorePath {
+    value: ::core::option::Option<i32>,
+}
+
+// rustfmt would drop the turbofish these structs exist to test
+#[rustfmt::skip]
+#[derive(Pipeline)]
+struct Turbofish {
+    value: Option::<i32>,
+}
+
+#[rustfmt::skip]
+#[derive(Pipeline)]
+struct StdTurbofish {
+    value: std::option::Option::<i32>,
+}
+
+#[rustfmt::skip]
+#[derive(Pipeline)]
+struct ResultTurbofish {
+    value: std::result::Result::<i32, ()>,
+}
+
+#[test]
+fn qualified_and_turbofish_paths() {
+    assert_eq!(StdPath { value: Some(1) }.process3(Some, Some), Some(1));
+    assert_eq!(CorePath { value: Some(1) }.process3(Some, Some), Some(1));
+    assert_eq!(Turbofish { value: Some(1) }.process3(Some, Some), Some(1));
+    assert_eq!(
+        StdTurbofish { value: Some(1) }.process3(Some, Some),
+        Some(1)
+    );
+    assert_eq!(ResultTurbofish { value: Ok(1) }.process3(Ok, Ok), Ok(1));
+}
+
+mod aliased {
+    use pipeline_derive::Pipeline;
+
+    // A crate-local alias must not capture the `Result` the generated methods return
+    type Result<T> = std::result::Result<T, String>;
+
+    #[derive(Pipeline)]
+    #[pipeline(ok_or, error_enum = "AliasedError")]
+    pub struct Aliased {
+        pub value: Option<i32>,
+    }
+
+    #[derive(Pipeline)]
+    pub struct AliasedResult {
+        pub value: std::result::Result<i32, String>,
+    }
+
+    pub fn run(p: &Aliased) -> Result<i32> {
+        let v = p.process3_ok_or(String::from("empty"), |v| Some(v + 1), Some)?;
+        Ok(v + p.try_process3(Some, Some).map_err(|err| err.to_string())?)
+    }
+}
+
+#[test]
+fn local_result_alias() {
+    use aliased::{Aliased, AliasedResult};
+    assert_eq!(aliased::run(&Aliased { value: Some(1) }), Ok(3));
+    assert_eq!(
+        aliased::run(&Aliased { value: None }),
+        Err(String::from("empty"))
+    );
+    let r = AliasedResult { value: Ok(1) };
+    assert_eq!(r.process3(|v| Ok(v + 1), Ok), Ok(2));
+    assert_eq!(
+        r.process3_map_err(|_| Err(String::from("bad")), Ok, |err| err.len()),
+        Err(3)
+    );
+    assert_eq!(
+        r.process3_all_errors(|_| Err(String::from("a")), |_| Err(String::from("b"))),
+        Err(vec![String::from("a"), String::from("b")])
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(both_receivers, export_trait = "FnPointerPipeline")]
+struct FnPointer {
+    value: Option<fn(i32) -> i32>,
+}
+
+#[derive(Pipeline)]
+struct HigherRankedFn {
+    value: Option<for<'b> fn(&'b str) -> &'b str>,
+}
+
+fn double(v: i32) -> i32 {
+    v * 2
+}
+
+fn triple(v: i32) -> i32 {
+    v * 3
+}
+
+fn same(s: &str) -> &str {
+    s
+}
+
+#[test]
+fn fn_pointers() {
+    let p = FnPointer {
+        value: Some(double),
+    };
+    let swapped: fn(i32) -> i32 = triple;
+    let f = p.process3(|f| Some(if f(1) == 2 { swapped } else { f }), Some);
+    assert_eq!(f.unwrap()(2), 6);
+    assert_eq!(
+        FnPointerPipeline::process4(&p, Some, Some, Some).unwrap()(4),
+        8
+    );
+    let all: Vec<fn(i32) -> i32> = p.process3_collect(Some, Some);
+    assert_eq!(all.len(), 2);
+    let h = HigherRankedFn { value: Some(same) };
+    assert_eq!(h.process3(Some, Some).unwrap()("x"), "x");
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Debug, PartialEq)]
+struct r#type(i32);
+
+#[derive(Pipeline)]
+#[pipeline(accessors, both_receivers, export_trait = "r#RawPipeline", version = 1)]
+struct RawField {
+    r#match: Option<r#type>,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Pipeline)]
+#[pipeline(mutate_field)]
+struct r#struct<r#T> {
+    r#fn: Result<r#T, ()>,
+}
+
+#[allow(non_camel_case_types)]
+type r#alias = Option<r#type>;
+
+#[derive(Pipeline)]
+#[pipeline(inner = "r#type")]
+struct RawInner {
+    r#in: r#alias,
+}
+
+#[test]
+fn raw_identifiers() {
+    let mut p = RawField {
+        r#match: Some(r#type(1)),
+    };
+    assert_eq!(p.process3(|t| Some(r#type(t.0 + 1)), Some), Some(r#type(2)));
+    p.set(r#type(5));
+    assert_eq!(RawPipeline::process3(&p, Some, Some), Some(r#type(5)));
+    let mut s = r#struct { r#fn: Ok(1) };
+    assert_eq!(s.process3(|v: i32| Ok(v + 1), Ok), Ok(2));
+    assert_eq!(s.r#fn, Ok(2));
+    let i = RawInner {
+        r#in: Some(r#type(1)),
+    };
+    assert_eq!(i.process3(Some, Some), Some(r#type(1)));
+}
+
+mod visibility {
+    use pipeline_derive::Pipeline;
+
+    #[derive(Pipeline)]
+    #[pipeline(export_trait = "PublicPipeline", error_enum = "PublicError")]
+    pub struct Public {
+        pub value: Option<i32>,
+    }
+
+    #[derive(Pipeline)]
+    #[pipeline(export_trait = "PrivatePipeline", error_enum = "PrivateError")]
+    struct Private {
+        value: Option<i32>,
+    }
+
+    pub mod nested {
+        #[derive(pipeline_derive::Pipeline)]
+        #[pipeline(export_trait = "RestrictedPipeline", error_enum = "RestrictedError")]
+        pub(in crate::visibility) struct Restricted {
+            pub(in crate::visibility) value: Option<i32>,
+        }
+    }
+
+    pub fn private_use() -> bool {
+        let p = Private { value: Some(1) };
+        PrivatePipeline::process3(&p, Some, Some) == Some(1) && p.try_process3(Some, Some).is_ok()
+    }
+
+    pub fn restricted_use() -> bool {
+        let r = nested::Restricted { value: None };
+        nested::RestrictedPipeline::process3(&r, Some, Some).is_none()
+            && r.try_process3(Some, Some) == Err(nested::RestrictedError::EarlyStop { step: 0 })
+    }
+}
+
+#[test]
+fn visibility_of_helper_types() {
+    use visibility::{Public, PublicError, PublicPipeline};
+    let p = Public { value: Some(2) };
+    assert_eq!(PublicPipeline::process3(&p, Some, Some), Some(2));
+    assert_eq!(
+        p.try_process3(|_| None, Some),
+        Err(PublicError::EarlyStop { step: 1 })
+    );
+    assert!(visibility::private_use());
+    assert!(visibility::restricted_use());
+}
+
+/// Documented struct.
+#[repr(C)]
+#[non_exhaustive]
+#[derive(Clone, Debug, Pipeline, PartialEq)]
+#[pipeline(max_arity = 3)]
+#[allow(dead_code)]
+#[cfg_attr(test, pipeline(both_receivers))]
+#[must_use]
+pub struct Attributed {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[repr(transparent)]
+#[pipeline(derive_debug)]
+struct Transparent<U> {
+    value: Option<U>,
+}
+
+#[test]
+fn outer_attributes() {
+    let p = Attributed { value: Some(1) };
+    assert_eq!(p.clone().into_process3(Some, Some), Some(1));
+    assert_eq!(p.process3(|v| Some(v + 1), Some), Some(2));
+    let t = Transparent { value: Some(()) };
+    assert_eq!(format!("{t:?}"), "Transparent { value: Some(..) }");
+}
+
+#[derive(Pipeline)]
+#[pipeline(as_fn)]
+struct Unsized<T: ?Sized> {
+    value: Option<Box<T>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(
+    export_trait = "UnsizedPipeline",
+    derive_debug,
+    accessors,
+    take,
+    error_enum = "UnsizedError",
+    both_receivers
+)]
+struct UnsizedWhere<T>
+where
+    T: ?Sized + ToString,
+{
+    value: Option<Box<T>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace, mutate_field)]
+struct UnsizedSlice<T: ?Sized> {
+    value: Option<Box<T>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(clone_bound = "T: Clone")]
+struct UnsizedResult<T: ?Sized> {
+    value: Result<Box<T>, String>,
+}
+
+#[test]
+fn unsized_params() {
+    let p: Unsized<str> = Unsized {
+        value: Some("ab".into()),
+    };
+    assert_eq!(p.process3(Some, Some).as_deref(), Some("ab"));
+    assert_eq!(p.as_fn(Some, Some)("c".into()).as_deref(), Some("c"));
+    let p: Unsized<i32> = Unsized {
+        value: Some(Box::new(1)),
+    };
+    assert_eq!(
+        p.process3(|b| Some(Box::new(*b + 1)), Some),
+        Some(Box::new(2))
+    );
+    let mut w: UnsizedWhere<str> = UnsizedWhere {
+        value: Some("x".into()),
+    };
+    assert_eq!(w.try_process3(Some, Some).unwrap().as_ref(), "x");
+    assert_eq!(
+        UnsizedPipeline::process3(&w, Some, Some).as_deref(),
+        Some("x")
+    );
+    assert!(format!("{w:?}").contains("Some"));
+    assert_eq!(w.process3_take(Some, Some).as_deref(), Some("x"));
+    let mut s: UnsizedSlice<[u8]> = UnsizedSlice {
+        value: Some(vec![1u8].into()),
+    };
+    let stored = UnsizedSlicePipeline::process3(&mut s, Some, Some);
+    assert_eq!(stored.as_deref(), Some(&[1u8][..]));
+    let r: UnsizedResult<i32> = UnsizedResult {
+        value: Ok(Box::new(3)),
+    };
+    assert_eq!(r.process3(Ok, Ok), Ok(Box::new(3)));
+}
+
+#[derive(Pipeline)]
+#[pipeline(
+    accessors,
+    both_receivers,
+    take,
+    first_some,
+    as_fn,
+    export_trait = "ShortPipeline",
+    error_enum = "ShortError",
+    derive_debug,
+    mode = "iterator",
+    with_step
+)]
+struct Short<'a> {
+    value: Option<&'a str>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(cow, namespace)]
+struct ShortCow<'a> {
+    value: Result<&'a str, String>,
+}
+
+#[test]
+fn short_lived_borrows() {
+    // Every value below borrows `owned`, which is dropped at the end of the test
+    let owned = "xy".to_string();
+    let mut p = Short {
+        value: Some(&owned),
+    };
+    assert_eq!(p.process3(|s| Some(&s[..1]), Some), Some("x"));
+    assert_eq!(p.process4_first_some(|_| None, Some, Some), Some("xy"));
+    assert_eq!(
+        p.process3_history(Some, Some),
+        Some(("xy", vec!["xy", "xy"]))
+    );
+    assert_eq!(p.process3_iter(Some, Some).count(), 2);
+    assert_eq!(p.try_process3(Some, Some), Ok("xy"));
+    assert_eq!(p.with_step(Some)(&Some), Some("xy"));
+    assert_eq!(p.as_fn(Some, Some)(&owned[1..]), Some("y"));
+    assert_eq!(p.process3_from(&owned[1..], Some, Some), Some("y"));
+    assert_eq!(p.process3_trace(Some, Some).len(), 2);
+    assert_eq!(ShortPipeline::process3(&p, Some, Some), Some("xy"));
+    assert_eq!(format!("{p:?}"), "Short { value: Some(..) }");
+    assert_eq!(p.process3_take(Some, Some), Some("xy"));
+    p.set(&owned);
+    assert_eq!(p.into_process3(Some, Some), Some("xy"));
+    let c = ShortCow { value: Ok(&owned) };
+    assert_eq!(ShortCowPipeline::process3(&c, Ok, Ok).as_deref(), Ok(&"xy"));
+}
+
+#[derive(Pipeline)]
+struct Bounded<T>
+where
+    T: Iterator,
+{
+    value: Option<T>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(
+    namespace,
+    mode = "iterator",
+    config,
+    from_option,
+    derive_debug,
+    accessors,
+    take,
+    both_receivers,
+    export_trait = "BoundedPipelineTrait",
+    error_enum = "BoundedError",
+    version = 1,
+    max_arity = 5
+)]
+struct BoundedMany<T: Iterator<Item = u8>>
+where
+    T: Clone + ExactSizeIterator,
+    T::Item: Copy,
+{
+    value: Option<T>,
+}
+
+#[derive(Clone, Copy)]
+struct Once(Option<u8>);
+
+impl Iterator for Once {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.take()
+    }
+}
+
+#[derive(Pipeline)]
+#[pipeline(copy)]
+struct BoundedCopy<T>
+where
+    T: Iterator + Copy,
+{
+    value: Result<T, u8>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(version = 2, mutate_field)]
+struct BoundedShared<T>
+where
+    T: Iterator,
+{
+    value: Arc<Mutex<Option<T>>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace, mode = "iterator", config)]
+struct HigherRanked<T>
+where
+    for<'a> &'a T: IntoIterator<Item = &'a u8>,
+{
+    value: Option<T>,
+}
+
+#[test]
+fn where_clause_bounds() {
+    let p = Bounded {
+        value: Some(vec![1, 2, 3].into_iter()),
+    };
+    let rest = p.process3(|mut it| it.next().map(|_| it), Some);
+    assert_eq!(rest.map(Iterator::collect::<Vec<_>>), Some(vec![2, 3]));
+    let q = BoundedMany {
+        value: Some(vec![1u8, 2].into_iter()),
+    };
+    let len = BoundedManyPipeline::process3(&q, Some, Some).map(|it| it.len());
+    assert_eq!(len, Some(2));
+    let sum = <BoundedMany<_> as BoundedPipelineTrait>::process3(&q, Some, Some);
+    assert_eq!(sum.map(Iterator::sum::<u8>), Some(3));
+    let c = BoundedCopy {
+        value: Ok(Once(Some(4))),
+    };
+    let drained = c.process3(Ok, |mut it| it.next().map(|_| it).ok_or(0));
+    assert_eq!(drained.map(|mut it| it.next()), Ok(None));
+    let s = BoundedShared {
+        value: Arc::new(Mutex::new(Some(vec![5].into_iter()))),
+    };
+    type Ints = std::vec::IntoIter<i32>;
+    let steps: Vec<fn(Ints) -> Option<Ints>> = vec![Some];
+    assert_eq!(s.process(steps).map(Iterator::count), Some(1));
+    let h = HigherRanked {
+        value: Some(vec![1u8, 2]),
+    };
+    let reversed = |mut v: Vec<u8>| {
+        v.reverse();
+        Some(v)
+    };
+    assert_eq!(
+        HigherRankedPipeline::process3(&h, Some, reversed),
+        Some(vec![2, 1])
+    );
+    assert_eq!(
+        HigherRankedPipeline::process3_iter(&h, Some, Some).count(),
+        2
+    );
+}
+
+struct NotClone;
+
+#[derive(Pipeline)]
+struct ExtraParams<K, V> {
+    value: Option<(V, fn(K))>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(namespace, config)]
+struct ExtraPhantom<K, V: Default> {
+    value: Result<V, std::marker::PhantomData<K>>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mode = "stepper")]
+struct ExtraStepper<'a, K: ?Sized, V, const N: usize> {
+    value: Option<([V; N], &'a K)>,
+}
+
+type Produces<K> = std::marker::PhantomData<fn() -> K>;
+
+#[derive(Pipeline)]
+#[pipeline(mode = "iterator", both_receivers)]
+struct ExtraIterator<K, V>
+where
+    K: 'static,
+{
+    value: Option<(V, Produces<K>)>,
+}
+
+#[test]
+fn params_outside_the_inner_type() {
+    // `NotClone` only appears in parameters the `Clone` bounds do not reach
+    let p: ExtraParams<NotClone, i32> = ExtraParams {
+        value: Some((1, |_| {})),
+    };
+    let doubled = p.process3(|(v, k)| Some((v + 1, k)), |(v, k)| Some((v * 2, k)));
+    assert_eq!(doubled.map(|(v, _)| v), Some(4));
+    let q: ExtraPhantom<NotClone, u8> = ExtraPhantom { value: Ok(1) };
+    assert_eq!(
+        ExtraPhantomPipeline::process3(&q, |v| Ok(v + 1), |v| Ok(v + 1)),
+        Ok(3)
+    );
+    let i: ExtraIterator<NotClone, u8> = ExtraIterator {
+        value: Some((1, std::marker::PhantomData)),
+    };
+    let moved = i.into_process3(|(v, k)| Some((v + 1, k)), Some);
+    assert_eq!(moved.map(|(v, _)| v), Some(2));
+    let r: ExtraStepper<str, u8, 2> = ExtraStepper {
+        value: Some(([1, 2], "k")),
+    };
+    let mut s = r.process3_stepper(|(a, k)| Some(([a[0] + 1, a[1]], k)), Some);
+    assert_eq!(s.step(), ExtraStepperState::Pending);
+    assert_eq!(s.step(), ExtraStepperState::Done(Some(([2, 2], "k"))));
+}
@@ -0,0 +1,8 @@
+//! Diagnostics of misused derives, checked against the expected compiler output in `tests/ui`.
+//! Regenerate the `.stderr` files with `TRYBUILD=overwrite cargo test --test ui`.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
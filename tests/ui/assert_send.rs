@@ -0,0 +1,10 @@
+use pipeline_derive::Pipeline;
+use std::rc::Rc;
+
+#[derive(Pipeline)]
+#[pipeline(assert_send)]
+struct P {
+    value: Option<Rc<i32>>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use pipeline_derive::Pipeline;
+
+// A method combining both fields needs the derive to accept more than one field
+#[derive(Pipeline)]
+struct P {
+    left: Option<i32>,
+    right: Option<i32>,
+}
+
+fn main() {}
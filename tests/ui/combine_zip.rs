@@ -0,0 +1,11 @@
+use pipeline_derive::Pipeline;
+
+// `combine = "zip"` would pair two `Option<T>` fields with `Option::zip`, but a struct with a
+// second field is rejected before any attribute is looked at
+#[derive(Pipeline)]
+struct Pair<T> {
+    first: Option<T>,
+    second: Option<T>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+struct P {
+    value: Option<i32>,
+    extra: u8,
+    more: String,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use pipeline_derive::Pipeline;
+
+struct NoClone;
+
+macro_rules! make {
+    ($name:ident, $ty:ty) => {
+        #[derive(Pipeline)]
+        pub struct $name {
+            value: $ty,
+        }
+    };
+}
+
+make!(P, Option<NoClone>);
+
+fn main() {}
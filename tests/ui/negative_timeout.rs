@@ -0,0 +1,9 @@
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+#[pipeline(timeout = -5)]
+struct P {
+    value: Option<i32>,
+}
+
+fn main() {}
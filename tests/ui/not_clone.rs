@@ -0,0 +1,10 @@
+use pipeline_derive::Pipeline;
+
+struct NoClone;
+
+#[derive(Pipeline)]
+struct P {
+    value: Option<NoClone>,
+}
+
+fn main() {}
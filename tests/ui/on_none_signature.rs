@@ -0,0 +1,11 @@
+use pipeline_derive::Pipeline;
+
+fn log_name(_name: &'static str) {}
+
+#[derive(Pipeline)]
+#[pipeline(on_none = log_name)]
+struct P {
+    value: Option<i32>,
+}
+
+fn main() {}
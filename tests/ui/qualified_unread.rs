@@ -0,0 +1,31 @@
+use pipeline_derive::Pipeline;
+
+extern crate pipeline_derive as renamed;
+
+#[derive(Debug)]
+#[pipeline_derive::pipeline(skip)]
+struct Underived {
+    value: Option<i32>,
+}
+
+#[pipeline_derive::pipeline(skip)]
+#[derive(Pipeline)]
+struct AboveDerive {
+    value: Option<i32>,
+}
+
+// A renamed crate path is only read once declared with `crate`
+#[derive(Pipeline)]
+#[renamed::pipeline(skip)]
+struct Undeclared {
+    value: Option<i32>,
+}
+
+// A derive that fails reports its own error, not an unread attribute
+#[derive(Pipeline)]
+#[pipeline_derive::pipeline(skip, skip)]
+struct Failing {
+    value: Option<i32>,
+}
+
+fn main() {}
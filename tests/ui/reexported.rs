@@ -0,0 +1,12 @@
+mod inner {
+    use pipeline_derive::Pipeline;
+
+    #[derive(Pipeline)]
+    pub struct P {
+        pub value: Vec<i32>,
+    }
+}
+
+pub use inner::P;
+
+fn main() {}
@@ -0,0 +1,8 @@
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+struct P {
+    value: Option<P>,
+}
+
+fn main() {}
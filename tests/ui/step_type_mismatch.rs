@@ -0,0 +1,11 @@
+use pipeline_derive::Pipeline;
+
+#[derive(Pipeline)]
+struct P {
+    value: Option<i32>,
+}
+
+fn main() {
+    let p = P { value: Some(1) };
+    let _ = p.process4(|v| Some(v + 1), |v| Some(v.to_string()), |v| Some(v * 2));
+}
@@ -0,0 +1,583 @@
+//! The method variants generated next to `process3`, each changing what a run takes or returns.
+
+use pipeline_derive::Pipeline;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::io::Write;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq)]
+struct NoClone(i32);
+
+#[derive(Pipeline)]
+#[pipeline(as_fn, with_step, process_mixed)]
+struct P {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(as_fn, process_mixed)]
+struct R {
+    value: Result<i32, String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(skip, as_fn, with_step)]
+struct Skipped {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(mutate_field, ok_or)]
+struct Mutated {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(cow)]
+struct Borrowed {
+    value: Option<String>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(as_fn)]
+struct Generic<T> {
+    value: Option<T>,
+}
+
+#[test]
+fn collect() {
+    let p = P { value: Some(1) };
+    let values: Vec<i32> = p.process3_collect(|v| Some(v + 1), |v| Some(v * 10));
+    assert_eq!(values, [2, 20]);
+    let unique: HashSet<i32> = p.process3_collect(Some, Some);
+    assert_eq!(unique, HashSet::from([1]));
+    let partial: Vec<i32> = p.process3_collect(|v| Some(v + 1), |_| None);
+    assert_eq!(partial, [2]);
+    let skipped: Vec<i32> = Skipped { value: Some(1) }.process3_collect(Some, Some);
+    assert!(skipped.is_empty());
+}
+
+#[derive(Pipeline)]
+#[pipeline(step_names = ["parse", "scale", "clamp"])]
+struct Named {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn trace() {
+    let p = P { value: Some(1) };
+    assert_eq!(
+        p.process3_trace(|v| Some(v + 1), |v| Some(v * 10)),
+        [(1, 2), (2, 20)]
+    );
+    assert_eq!(p.process3_trace(|v| Some(v + 1), |_| None), [(1, 2)]);
+    assert_eq!(P { value: None }.process3_trace(Some, Some), []);
+    let n = Named { value: Ok(3) };
+    assert_eq!(
+        n.process3_trace(Ok, |v| Ok(v * 2)),
+        [(1, "parse", 3), (2, "scale", 6)]
+    );
+    let c = Borrowed {
+        value: Some("a".into()),
+    };
+    let appended = c.process3_trace(Some, |s| Some(Cow::Owned(s.into_owned() + "b")));
+    assert_eq!(appended, [(1, "a".to_string()), (2, "ab".to_string())]);
+}
+
+#[test]
+fn tuple() {
+    let p = P { value: Some(1) };
+    assert_eq!(
+        p.process3_tuple(|v| Some(v + 1), |v| Some(v * 10)),
+        (Some(2), Some(20))
+    );
+    assert_eq!(p.process3_tuple(|_| None, Some), (None, None));
+    let r = R { value: Ok(1) };
+    let failed = r.process3_tuple(Ok, |_| Err("bad".into()));
+    assert_eq!(failed, (Ok(1), Err("bad".to_string())));
+    let e = R {
+        value: Err("e".into()),
+    };
+    assert_eq!(
+        e.process3_tuple(Ok, Ok),
+        (Err("e".to_string()), Err("e".to_string()))
+    );
+}
+
+#[test]
+fn with_count() {
+    let p = P { value: Some(1) };
+    assert_eq!(
+        p.process3_with_count(|v| Some(v + 1), |v| Some(v * 2)),
+        (Some(4), 2)
+    );
+    assert_eq!(p.process3_with_count(|_| None, Some), (None, 1));
+    assert_eq!(p.process3_with_count(Some, |_| None), (None, 2));
+    assert_eq!(P { value: None }.process3_with_count(Some, Some), (None, 0));
+    assert_eq!(
+        Skipped { value: Some(1) }.process3_with_count(Some, Some),
+        (None, 0)
+    );
+}
+
+#[test]
+fn history() {
+    let p = P { value: Some(2) };
+    let history = p.process3_history(|v| Some(v + 1), |v| Some(v * 10));
+    assert_eq!(history, Some((30, vec![3, 30])));
+    assert_eq!(p.process3_history(|v| Some(v + 1), |_| None), None);
+    let mut m = Mutated { value: Some(1) };
+    assert_eq!(m.process3_history(|v| Some(v + 1), |_| None), None);
+    assert_eq!(m.value, Some(2));
+}
+
+#[test]
+fn total_time() {
+    let p = P { value: Some(1) };
+    let slow = |v: i32| {
+        std::thread::sleep(Duration::from_millis(5));
+        Some(v + 1)
+    };
+    let (result, took) = p.process3_total_time(slow, Some);
+    assert_eq!(result, Some(2));
+    assert!(took >= Duration::from_millis(5));
+    assert_eq!(p.process3_total_time(|_| None, Some).0, None);
+}
+
+#[derive(Pipeline)]
+#[pipeline(step_names = ["parse", "check", "emit"], mutate_field)]
+struct NamedMutated {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn diag() {
+    let p = P { value: Some(1) };
+    let mut diag = Vec::new();
+    assert_eq!(p.process3_diag(|v| Some(v + 1), Some, &mut diag), Some(2));
+    assert_eq!(diag, ["step 1 succeeded", "step 2 succeeded"]);
+    let mut diag = Vec::new();
+    assert_eq!(p.process3_diag(Some, |_| None, &mut diag), None);
+    assert_eq!(diag, ["step 1 succeeded", "step 2 returned None"]);
+    let mut diag = Vec::new();
+    assert_eq!(P { value: None }.process3_diag(Some, Some, &mut diag), None);
+    assert_eq!(diag, ["no value to process"]);
+    let mut r = NamedMutated { value: Ok(1) };
+    let mut diag = Vec::new();
+    let failed = r.process3_diag(|v| Ok(v + 1), |_| Err("bad".into()), &mut diag);
+    assert_eq!(failed, Err("bad".to_string()));
+    assert_eq!(
+        diag,
+        ["step parse succeeded", "step check returned an error"]
+    );
+    assert_eq!(r.value, Ok(2));
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("closed"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn to_writer() {
+    let mut out = Vec::new();
+    let p = P { value: Some(1) };
+    assert_eq!(
+        p.process3_to_writer(|v| Some(v + 1), |_| None, &mut out),
+        None
+    );
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "step 1 succeeded\nstep 2 returned None\n"
+    );
+    let mut out = Vec::new();
+    let sink: &mut dyn Write = &mut out;
+    assert_eq!(P { value: None }.process3_to_writer(Some, Some, sink), None);
+    assert_eq!(String::from_utf8(out).unwrap(), "no value to process\n");
+    // Write errors don't change the result
+    assert_eq!(p.process3_to_writer(Some, Some, &mut Broken), Some(1));
+}
+
+#[test]
+fn batch() {
+    let p = Generic::<i32> { value: None };
+    let even = |v: i32| (v % 2 == 0).then_some(v);
+    let results = p.process3_batch(vec![1, 2, 3, 4], even, |v| Some(v * 10));
+    assert_eq!(results, [None, Some(20), None, Some(40)]);
+    let n = Generic::<NoClone> { value: None };
+    assert_eq!(n.process3_batch((0..3).map(NoClone), Some, Some).len(), 3);
+    let r = R { value: Ok(0) };
+    let positive = |v: i32| if v > 0 { Ok(v) } else { Err("neg".to_string()) };
+    assert_eq!(
+        r.process3_batch([1, -1], positive, Ok),
+        [Ok(1), Err("neg".into())]
+    );
+}
+
+#[test]
+fn map() {
+    let mut m = Mutated { value: Some(4) };
+    let label = m.process3_map(|v| Some(v + 1), |v| Some(v * 2), |v| format!("#{v}"));
+    assert_eq!(label, Some("#10".to_string()));
+    assert_eq!(m.value, Some(10));
+    assert_eq!(m.process3_map(|_| None, Some, |v: i32| v.to_string()), None);
+    assert_eq!(
+        P { value: Some(1) }.process3_map(Some, Some, |v| v as u8),
+        Some(1u8)
+    );
+}
+
+#[derive(Debug, PartialEq)]
+enum AppError {
+    Parse(String),
+}
+
+#[test]
+fn map_err() {
+    let r = R { value: Ok(2) };
+    assert_eq!(
+        r.process3_map_err(|v| Ok(v + 1), |v| Ok(v * 2), AppError::Parse),
+        Ok(6)
+    );
+    let failed = r.process3_map_err(|_| Err("bad".into()), Ok, AppError::Parse);
+    assert_eq!(failed, Err(AppError::Parse("bad".into())));
+    let e = R {
+        value: Err("seed".into()),
+    };
+    assert_eq!(e.process3_map_err(Ok, Ok, |e| e.len()), Err(4));
+}
+
+#[derive(Pipeline)]
+struct Dropped {
+    value: Option<i32>,
+}
+
+impl Drop for Dropped {
+    fn drop(&mut self) {}
+}
+
+#[test]
+fn chain() {
+    let p = P { value: Some(1) }
+        .process3_chain(|v| Some(v + 1), |v| Some(v * 10))
+        .process3_chain(|v| Some(v + 1), |v| Some(v * 2));
+    assert_eq!(p.value, Some(42));
+    let p = p.process3_chain(|_| None, Some).process3_chain(Some, Some);
+    assert_eq!(p.value, None);
+    let r = R { value: Ok(1) }.process3_chain(|v| Ok(v + 1), |_| Err("bad".into()));
+    assert_eq!(r.value, Err("bad".to_string()));
+    let d = Dropped { value: Some(1) }.process3_chain(|v| Some(v + 1), Some);
+    assert_eq!(d.value, Some(2));
+}
+
+#[test]
+fn into() {
+    let p = P { value: Some(1) };
+    assert_eq!(p.process3_into(|v| v + 1, |v| v * 3), Some(6));
+    assert_eq!(p.process3_into(|v| Some(v + 1), |v| v * 3), Some(6));
+    assert_eq!(p.process3_into(|_| None, |v| v * 3), None);
+    assert_eq!(p.process3_into(|v| v, |v| (v > 0).then_some(v)), Some(1));
+}
+
+struct Config {
+    factor: i32,
+    limit: i32,
+}
+
+#[test]
+fn ctx() {
+    let cfg = Config {
+        factor: 3,
+        limit: 10,
+    };
+    let p = P { value: Some(2) };
+    let scale = |c: &Config, v: i32| Some(v * c.factor);
+    let clamp = |c: &Config, v: i32| (v < c.limit).then_some(v);
+    assert_eq!(p.process3_ctx(&cfg, scale, clamp), Some(6));
+    assert_eq!(
+        p.process3_ctx(&Config { factor: 9, ..cfg }, scale, clamp),
+        None
+    );
+    let mut m = Mutated { value: Some(1) };
+    m.process3_ctx(&5, |c, v| Some(v + c), |c, v| Some(v * c));
+    assert_eq!(m.value, Some(30));
+    // The context may be unsized
+    let n = Generic { value: Some(1u8) };
+    assert_eq!(
+        n.process3_ctx(&[1u8, 2][..], |c, v| Some(v + c[0]), |c, v| Some(v + c[1])),
+        Some(4)
+    );
+}
+
+#[test]
+fn mixed() {
+    let p = P { value: Some(1) };
+    assert_eq!(p.process_mixed(|v| v.checked_add(1), |v| v * 3), Some(6));
+    assert_eq!(p.process_mixed(|_| None, |v| v * 3), None);
+    let r = R { value: Ok(2) };
+    assert_eq!(r.process_mixed(|v| Ok(v + 1), |v| v * 2), Ok(6));
+    assert_eq!(
+        r.process_mixed(|_| Err("no".into()), |v| v),
+        Err("no".to_string())
+    );
+}
+
+#[test]
+fn within() {
+    let p = P { value: Some(1) };
+    let budget = Duration::from_secs(5);
+    assert_eq!(
+        p.process3_within(budget, |v| Some(v + 1), |v| Some(v * 2)),
+        Some(4)
+    );
+    let ran = Cell::new(false);
+    let slow = |v| {
+        std::thread::sleep(Duration::from_millis(20));
+        Some(v)
+    };
+    let late = |v| {
+        ran.set(true);
+        Some(v)
+    };
+    assert_eq!(
+        p.process3_within(Duration::from_millis(1), slow, late),
+        None
+    );
+    assert!(!ran.get());
+}
+
+#[derive(Debug, PartialEq)]
+struct MyErr(&'static str);
+
+fn run(p: &mut Mutated) -> Result<i32, MyErr> {
+    let v = p.process3_ok_or(MyErr("empty"), |v| Some(v + 1), Some)?;
+    p.process3_ok_or(MyErr("second"), |w| Some(w + v), |_| None)
+}
+
+#[test]
+fn ok_or() {
+    let mut p = Mutated { value: Some(1) };
+    assert_eq!(run(&mut p), Err(MyErr("second")));
+    assert_eq!(p.value, Some(4));
+    assert_eq!(run(&mut Mutated { value: None }), Err(MyErr("empty")));
+}
+
+#[derive(Pipeline)]
+struct Form {
+    value: Result<String, String>,
+}
+
+#[test]
+fn all_errors() {
+    let form = Form {
+        value: Ok("ab".into()),
+    };
+    let long = |s: String| {
+        if s.len() > 3 {
+            Ok(s)
+        } else {
+            Err(format!("{s} too short"))
+        }
+    };
+    let upper = |s: String| {
+        if s.chars().all(char::is_uppercase) {
+            Ok(s)
+        } else {
+            Err("not uppercase".to_string())
+        }
+    };
+    let errors = vec!["ab too short".to_string(), "not uppercase".to_string()];
+    assert_eq!(form.process3_all_errors(long, upper), Err(errors));
+    // Values returned by passing steps are discarded
+    assert_eq!(
+        form.process3_all_errors(Ok, |_| Ok("other".into())),
+        Ok("ab".to_string())
+    );
+    let empty = Form {
+        value: Err("empty".into()),
+    };
+    assert_eq!(
+        empty.process3_all_errors(Ok, Ok),
+        Err(vec!["empty".to_string()])
+    );
+}
+
+#[derive(Pipeline)]
+#[pipeline(run_dyn)]
+struct Dyn {
+    value: Option<i32>,
+}
+
+#[test]
+fn run_dyn() {
+    let p = Dyn { value: Some(1) };
+    let mut calls = 0;
+    let mut total = 0;
+    let steps: Vec<Box<dyn FnMut(i32) -> Option<i32>>> = vec![
+        Box::new(|v| {
+            calls += 1;
+            Some(v + 1)
+        }),
+        Box::new(move |v| {
+            total += v;
+            Some(v * total)
+        }),
+    ];
+    assert_eq!(p.run_dyn(steps), Some(4));
+    assert_eq!(calls, 1);
+    assert_eq!(p.run_dyn(Vec::new()), Some(1));
+}
+
+#[derive(Pipeline)]
+#[pipeline(apply_all)]
+struct Repeated {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(apply_all, mutate_field)]
+struct RepeatedMutated {
+    value: Option<i32>,
+}
+
+#[test]
+fn apply_all() {
+    let p = Repeated { value: Some(1) };
+    assert_eq!(p.apply_all(|v| Some(v + 1), 5), Some(6));
+    assert_eq!(p.apply_all(|v| Some(v + 1), 0), Some(1));
+    let calls = Cell::new(0);
+    let capped = |v: i32| {
+        calls.set(calls.get() + 1);
+        (v < 3).then_some(v + 1)
+    };
+    assert_eq!(p.apply_all(capped, 10), None);
+    assert_eq!(calls.get(), 3);
+    let mut m = RepeatedMutated { value: Some(250) };
+    assert_eq!(m.apply_all(|v| (v < 254).then_some(v + 2), 5), None);
+    assert_eq!(m.value, Some(254));
+}
+
+#[test]
+fn as_fn() {
+    let p = Generic { value: None::<i32> };
+    let keep_even = |v: i32| (v % 2 == 0).then_some(v);
+    let evens: Vec<i32> = (1..=6)
+        .filter_map(p.as_fn(keep_even, |v| Some(v * 10)))
+        .collect();
+    assert_eq!(evens, [20, 40, 60]);
+    let n = Generic {
+        value: None::<NoClone>,
+    };
+    let f = n.as_fn(|v: NoClone| Some(NoClone(v.0 + 1)), Some);
+    assert_eq!(f(NoClone(1)), Some(NoClone(2)));
+    assert_eq!(
+        (1..3)
+            .filter_map(Skipped { value: None }.as_fn(Some, Some))
+            .count(),
+        0
+    );
+    let r = R { value: Ok(0) };
+    let parsed: Result<Vec<i32>, String> =
+        [1, 2].into_iter().map(r.as_fn(Ok, |v| Ok(v + 1))).collect();
+    assert_eq!(parsed, Ok(vec![2, 3]));
+}
+
+fn make(v: i32) -> impl Fn(i32) -> Option<i32> {
+    P { value: Some(v) }.as_fn(Some, Some)
+}
+
+#[test]
+fn as_fn_outlives_struct() {
+    assert_eq!(make(1)(2), Some(2));
+}
+
+#[derive(Pipeline)]
+#[pipeline(compose)]
+struct Composed {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(compose, skip)]
+struct ComposedSkipped {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(compose)]
+struct ComposedResult {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn compose() {
+    let parser = Composed { value: None };
+    // The composed closure outlives the struct whose `as_fn` it was built from
+    let both = {
+        let scaler = Generic { value: Some(0) };
+        parser.compose(
+            |v| Some(v + 1),
+            |v| (v > 0).then_some(v),
+            scaler.as_fn(|v| Some(v * 10), Some),
+        )
+    };
+    let out: Vec<i32> = vec![-1, 0, 1, 2].into_iter().filter_map(both).collect();
+    assert_eq!(out, [10, 20, 30]);
+    assert_eq!(
+        ComposedSkipped { value: None }.compose(Some, Some, Some)(1),
+        None
+    );
+    let r = ComposedResult { value: Ok(0) }.compose(Ok, Ok, |v: i32| {
+        if v < 0 { Err("neg".to_string()) } else { Ok(v) }
+    });
+    assert_eq!((r(1), r(-1)), (Ok(1), Err("neg".to_string())));
+}
+
+#[test]
+fn with_step() {
+    let partial = {
+        let p = P { value: Some(2) };
+        p.with_step(|v| Some(v + 1))
+    };
+    assert_eq!(partial(&|v| Some(v * 10)), Some(30));
+    assert_eq!(partial(&|_| None), None);
+    let twice = |v: i32| Some(v * 2);
+    let seconds: [&dyn Fn(i32) -> Option<i32>; 2] = [&twice, &Some];
+    let results: Vec<_> = seconds.into_iter().map(&partial).collect();
+    assert_eq!(results, [Some(6), Some(3)]);
+    assert_eq!(Skipped { value: Some(1) }.with_step(Some)(&Some), None);
+}
+
+#[derive(Pipeline)]
+#[pipeline(chain_type = "try")]
+struct TryChained {
+    value: Option<i32>,
+}
+
+#[derive(Pipeline)]
+#[pipeline(chain_type = "try")]
+struct TryChainedResult {
+    value: Result<i32, String>,
+}
+
+#[test]
+fn try_chain_type() {
+    let p = TryChained { value: Some(1) };
+    assert_eq!(p.process3(|v| Some(v + 1), |v| Some(v * 2)), Some(4));
+    assert_eq!(p.process4(|_| None, |_| panic!("step ran"), Some), None);
+    let r = TryChainedResult { value: Ok(1) };
+    assert_eq!(r.process3(|v| Ok(v + 1), |v| Ok(v * 2)), Ok(4));
+    assert_eq!(
+        r.process4(|_| Err("first".to_string()), |_| panic!("step ran"), Ok),
+        Err("first".to_string())
+    );
+}